@@ -7,13 +7,18 @@ use crate::http::Response;
 #[derive(Debug)]
 pub struct Error {
   kind: Kind,
-  cause: Option<Box<dyn std::error::Error>>,
+  cause: Option<Box<dyn std::error::Error + Send + Sync>>,
 }
 
 #[derive(Debug)]
 pub enum Kind {
   Parse(Parse),
   UnsupportedVersion,
+  PayloadTooLarge,
+  /// The client took longer than allowed to send the request headers or body.
+  Timeout,
+  /// A path parameter was missing or couldn't be parsed into the requested type.
+  Param(String),
   Io,
 }
 
@@ -21,21 +26,48 @@ impl Error {
   pub fn new(kind: Kind) -> Self {
     Self { kind, cause: None }
   }
-  pub fn with<E: Into<Box<dyn std::error::Error>>>(mut self, err: E) -> Self {
+  pub fn with<E: Into<Box<dyn std::error::Error + Send + Sync>>>(mut self, err: E) -> Self {
     self.cause = Some(err.into());
     self
   }
   pub fn new_io(err: io::Error) -> Self {
     Self::new(Kind::Io).with(err)
   }
+  pub fn kind(&self) -> &Kind {
+    &self.kind
+  }
 }
 
+/// Why a request couldn't be parsed. Offsets count bytes from the start of the request.
 #[derive(Debug)]
 pub enum Parse {
-  Method,
-  Uri,
-  Header,
-  Version,
+  Method(usize),
+  Uri(usize),
+  Header(usize),
+  Version(usize),
+  RequestLineTooLong(usize),
+  TooManyHeaders,
+  HeaderTooLarge(usize),
+  ContentLength,
+  Chunk,
+  TransferEncoding,
+}
+
+impl fmt::Display for Parse {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Method(at) => write!(f, "invalid method parsed at byte {at}"),
+      Self::Uri(at) => write!(f, "invalid uri parsed at byte {at}"),
+      Self::Header(at) => write!(f, "invalid header parsed at byte {at}"),
+      Self::Version(at) => write!(f, "invalid version parsed at byte {at}"),
+      Self::RequestLineTooLong(at) => write!(f, "request line too long at byte {at}"),
+      Self::TooManyHeaders => write!(f, "too many headers"),
+      Self::HeaderTooLarge(at) => write!(f, "header too large at byte {at}"),
+      Self::ContentLength => write!(f, "invalid content length"),
+      Self::Chunk => write!(f, "invalid chunked body parsed"),
+      Self::TransferEncoding => write!(f, "invalid transfer encoding"),
+    }
+  }
 }
 
 impl From<Parse> for Error {
@@ -46,30 +78,37 @@ impl From<Parse> for Error {
 
 impl std::error::Error for Error {
   fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-    self.cause.as_deref()
+    self.cause.as_deref().map(|e| e as _)
   }
 }
 
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(
-      f,
-      "{}",
-      match self.kind {
-        Kind::Parse(Parse::Uri) => "invalid uri parsed",
-        Kind::Parse(Parse::Header) => "invalid header parsed",
-        Kind::Parse(Parse::Method) => "invalid method parsed",
-        Kind::Parse(Parse::Version) => "invalid version parsed",
-        Kind::UnsupportedVersion => "unsupported version",
-        Kind::Io => "io error",
-      }
-    )
+    match &self.kind {
+      Kind::Parse(err) => err.fmt(f),
+      Kind::UnsupportedVersion => write!(f, "unsupported version"),
+      Kind::PayloadTooLarge => write!(f, "payload too large"),
+      Kind::Timeout => write!(f, "timed out reading the request"),
+      Kind::Param(name) => write!(f, "invalid path parameter `{name}`"),
+      Kind::Io => match &self.cause {
+        Some(cause) => write!(f, "io error: {cause}"),
+        None => write!(f, "io error"),
+      },
+    }
   }
 }
 
 impl IntoResponse for Error {
   fn into_response(self) -> crate::http::Response {
     match self.kind {
+      Kind::Parse(Parse::RequestLineTooLong(_)) => Response::builder()
+        .status(414)
+        .body("URI too long.")
+        .unwrap(),
+      Kind::Parse(Parse::TooManyHeaders | Parse::HeaderTooLarge(_)) => Response::builder()
+        .status(431)
+        .body("Request header fields too large.")
+        .unwrap(),
       Kind::Parse(_) => Response::builder()
         .status(400)
         .body("Sorry! Bad request.")
@@ -78,6 +117,18 @@ impl IntoResponse for Error {
         .status(505)
         .body("HTTP version not supported.")
         .unwrap(),
+      Kind::PayloadTooLarge => Response::builder()
+        .status(413)
+        .body("Payload too large.")
+        .unwrap(),
+      Kind::Timeout => Response::builder()
+        .status(408)
+        .body("Request timeout.")
+        .unwrap(),
+      Kind::Param(name) => Response::builder()
+        .status(400)
+        .body(format!("Invalid path parameter `{name}`."))
+        .unwrap(),
       Kind::Io => Response::builder()
         .status(500)
         .body("Sorry! Internal server error.")
@@ -1,21 +1,26 @@
 use std::future::Future;
 
 use async_trait::async_trait;
+
+/// Something that answers requests. Handlers take `&self` and are shared between connections,
+/// so requests run in parallel. State that every request sees belongs in the handler, or in an
+/// `Arc` captured by a closure; state that requests change needs its own synchronization, such
+/// as a `Mutex` or an atomic, around just the data that needs it.
 #[async_trait]
-pub trait Handler<Request: Send> {
+pub trait Handler<Request: Send>: Send + Sync {
   type Response: Send;
-  async fn call(&mut self, request: Request) -> Self::Response;
+  async fn call(&self, request: Request) -> Self::Response;
 }
 #[async_trait]
 impl<F, Fut, Request, Response> Handler<Request> for F
 where
-  F: FnMut(Request) -> Fut + Send,
+  F: Fn(Request) -> Fut + Send + Sync,
   Fut: Future<Output = Response> + Send,
   Request: Send + 'static,
   Response: Send,
 {
   type Response = Response;
-  async fn call(&mut self, request: Request) -> Self::Response {
+  async fn call(&self, request: Request) -> Self::Response {
     (self)(request).await
   }
 }
@@ -1,40 +1,188 @@
+use std::fmt;
+
 use super::*;
 
-#[derive(Debug, Default)]
-pub enum StatusCode {
+macro_rules! status_codes {
+  ($($(#[$attr:meta])* $name:ident = $code:literal, $reason:literal;)+) => {
+    /// An HTTP response status. Every code in the IANA registry has its own variant; anything
+    /// else can be expressed with [`StatusCode::custom`].
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+    pub enum StatusCode {
+      $($(#[$attr])* $name,)+
+      /// A code outside the registry, or a registered one with a different reason phrase.
+      Custom(u16, String),
+    }
+
+    impl StatusCode {
+      pub fn as_u16(&self) -> u16 {
+        match self {
+          $(Self::$name => $code,)+
+          Self::Custom(code, _) => *code,
+        }
+      }
+      pub fn reason(&self) -> &str {
+        match self {
+          $(Self::$name => $reason,)+
+          Self::Custom(_, reason) => reason,
+        }
+      }
+      /// Looks up a registered code.
+      pub fn from_u16(code: u16) -> Option<Self> {
+        match code {
+          $($code => Some(Self::$name),)+
+          _ => None,
+        }
+      }
+    }
+  };
+}
+
+status_codes! {
+  Continue = 100, "Continue";
+  SwitchingProtocols = 101, "Switching Protocols";
+  Processing = 102, "Processing";
+  EarlyHints = 103, "Early Hints";
+
   #[default]
-  Ok,
-  Created,
-  NotFound,
-  Unauthorized,
-  BadRequest,
-  InternalServerError,
+  Ok = 200, "OK";
+  Created = 201, "Created";
+  Accepted = 202, "Accepted";
+  NonAuthoritativeInformation = 203, "Non-Authoritative Information";
+  NoContent = 204, "No Content";
+  ResetContent = 205, "Reset Content";
+  PartialContent = 206, "Partial Content";
+  MultiStatus = 207, "Multi-Status";
+  AlreadyReported = 208, "Already Reported";
+  ImUsed = 226, "IM Used";
+
+  MultipleChoices = 300, "Multiple Choices";
+  MovedPermanently = 301, "Moved Permanently";
+  Found = 302, "Found";
+  SeeOther = 303, "See Other";
+  NotModified = 304, "Not Modified";
+  UseProxy = 305, "Use Proxy";
+  TemporaryRedirect = 307, "Temporary Redirect";
+  PermanentRedirect = 308, "Permanent Redirect";
+
+  BadRequest = 400, "Bad Request";
+  Unauthorized = 401, "Unauthorized";
+  PaymentRequired = 402, "Payment Required";
+  Forbidden = 403, "Forbidden";
+  NotFound = 404, "Not Found";
+  MethodNotAllowed = 405, "Method Not Allowed";
+  NotAcceptable = 406, "Not Acceptable";
+  ProxyAuthenticationRequired = 407, "Proxy Authentication Required";
+  RequestTimeout = 408, "Request Timeout";
+  Conflict = 409, "Conflict";
+  Gone = 410, "Gone";
+  LengthRequired = 411, "Length Required";
+  PreconditionFailed = 412, "Precondition Failed";
+  ContentTooLarge = 413, "Content Too Large";
+  UriTooLong = 414, "URI Too Long";
+  UnsupportedMediaType = 415, "Unsupported Media Type";
+  RangeNotSatisfiable = 416, "Range Not Satisfiable";
+  ExpectationFailed = 417, "Expectation Failed";
+  MisdirectedRequest = 421, "Misdirected Request";
+  UnprocessableContent = 422, "Unprocessable Content";
+  Locked = 423, "Locked";
+  FailedDependency = 424, "Failed Dependency";
+  TooEarly = 425, "Too Early";
+  UpgradeRequired = 426, "Upgrade Required";
+  PreconditionRequired = 428, "Precondition Required";
+  TooManyRequests = 429, "Too Many Requests";
+  RequestHeaderFieldsTooLarge = 431, "Request Header Fields Too Large";
+  UnavailableForLegalReasons = 451, "Unavailable For Legal Reasons";
+
+  InternalServerError = 500, "Internal Server Error";
+  NotImplemented = 501, "Not Implemented";
+  BadGateway = 502, "Bad Gateway";
+  ServiceUnavailable = 503, "Service Unavailable";
+  GatewayTimeout = 504, "Gateway Timeout";
+  HttpVersionNotSupported = 505, "HTTP Version Not Supported";
+  VariantAlsoNegotiates = 506, "Variant Also Negotiates";
+  InsufficientStorage = 507, "Insufficient Storage";
+  LoopDetected = 508, "Loop Detected";
+  NotExtended = 510, "Not Extended";
+  NetworkAuthenticationRequired = 511, "Network Authentication Required";
 }
 
-impl ToString for StatusCode {
-  fn to_string(&self) -> String {
-    match self {
-      Self::Ok => "200 OK",
-      Self::Created => "201 Created",
-      Self::NotFound => "404 Not Found",
-      Self::Unauthorized => "403 Unauthorized",
-      Self::BadRequest => "400 Bad Request",
-      Self::InternalServerError => "500 Internal Server Error",
+impl StatusCode {
+  /// A status with any three-digit code and reason phrase.
+  pub fn custom(code: u16, reason: &str) -> Result<Self, HttpError> {
+    if !(100..=999).contains(&code) {
+      return Err(HttpError::InvalidResponseCode(code));
     }
-    .to_string()
+    if reason.bytes().any(|b| b.is_ascii_control() && b != b'\t') {
+      return Err(HttpError::InvalidHeaderValue(
+        "reason phrase".into(),
+        reason.into(),
+      ));
+    }
+    Ok(Self::Custom(code, reason.into()))
+  }
+  pub fn is_informational(&self) -> bool {
+    (100..200).contains(&self.as_u16())
+  }
+  /// Whether a response with this status can have content. 1xx, 204 and 304 responses never
+  /// do, so they carry no body and no `Content-Length` (RFC 9110, sections 6.4.1 and 8.6).
+  pub fn allows_body(&self) -> bool {
+    !self.is_informational() && !matches!(self.as_u16(), 204 | 304)
+  }
+  pub fn is_success(&self) -> bool {
+    (200..300).contains(&self.as_u16())
+  }
+  pub fn is_redirection(&self) -> bool {
+    (300..400).contains(&self.as_u16())
+  }
+  pub fn is_client_error(&self) -> bool {
+    (400..500).contains(&self.as_u16())
+  }
+  pub fn is_server_error(&self) -> bool {
+    (500..600).contains(&self.as_u16())
+  }
+}
+
+impl fmt::Display for StatusCode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} {}", self.as_u16(), self.reason())
   }
 }
 impl TryFrom<u16> for StatusCode {
   type Error = HttpError;
+  /// Registered codes map to their variant; other three-digit codes get an empty reason phrase.
   fn try_from(value: u16) -> std::result::Result<Self, Self::Error> {
-    match value {
-      200 => Ok(Self::Ok),
-      201 => Ok(Self::Created),
-      404 => Ok(Self::NotFound),
-      403 => Ok(Self::Unauthorized),
-      400 => Ok(Self::BadRequest),
-      500 => Ok(Self::InternalServerError),
-      _ => Err(HttpError::InvalidResponseCode(value)),
+    match Self::from_u16(value) {
+      Some(code) => Ok(code),
+      None => Self::custom(value, ""),
     }
   }
 }
+impl From<StatusCode> for u16 {
+  fn from(value: StatusCode) -> Self {
+    value.as_u16()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn registered_and_custom_codes() {
+    let code = StatusCode::try_from(505).unwrap();
+    assert_eq!(code, StatusCode::HttpVersionNotSupported);
+    assert_eq!(code.to_string(), "505 HTTP Version Not Supported");
+    assert!(code.is_server_error());
+
+    let code = StatusCode::try_from(299).unwrap();
+    assert_eq!(code.as_u16(), 299);
+    assert!(code.is_success());
+
+    let code = StatusCode::custom(420, "Enhance Your Calm").unwrap();
+    assert_eq!(code.to_string(), "420 Enhance Your Calm");
+    assert!(code.is_client_error());
+
+    assert!(StatusCode::try_from(99).is_err());
+    assert!(StatusCode::custom(200, "OK\r\nX-Injected: 1").is_err());
+  }
+}
@@ -1,15 +1,91 @@
-pub type Headers = std::collections::HashMap<String, String>;
+use std::fmt;
 
-#[derive(Debug, Default)]
+use tokio::sync::mpsc;
+
+use super::HttpError;
+
+#[derive(Default)]
 pub struct Body {
-  inner: Vec<u8>,
+  inner: Inner,
+}
+enum Inner {
+  Full(Vec<u8>),
+  Stream(mpsc::Receiver<Vec<u8>>),
+}
+impl Default for Inner {
+  fn default() -> Self {
+    Self::Full(Vec::new())
+  }
 }
 impl Body {
   pub fn new(inner: Vec<u8>) -> Self {
-    Self { inner }
+    Self {
+      inner: Inner::Full(inner),
+    }
+  }
+  /// Creates a body whose chunks are produced through the returned sender while the response is
+  /// being written. At most `capacity` chunks are buffered, so a producer that outpaces the
+  /// client waits in [`BodySender::send`]. The body ends when the sender is dropped.
+  pub fn channel(capacity: usize) -> (BodySender, Self) {
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+    let body = Self {
+      inner: Inner::Stream(rx),
+    };
+    (BodySender { inner: tx }, body)
   }
+  /// The bytes of a body held in memory. Streaming bodies have none until they are read.
   pub fn get_bytes(&self) -> &[u8] {
-    &self.inner[..]
+    match &self.inner {
+      Inner::Full(bytes) => &bytes[..],
+      Inner::Stream(_) => &[],
+    }
+  }
+  /// The size of the body, if it is known up front.
+  pub fn content_length(&self) -> Option<usize> {
+    match &self.inner {
+      Inner::Full(bytes) => Some(bytes.len()),
+      Inner::Stream(_) => None,
+    }
+  }
+  pub fn is_stream(&self) -> bool {
+    matches!(self.inner, Inner::Stream(_))
+  }
+  /// Takes the next piece of the body, returning `None` once it has all been read.
+  pub async fn next_chunk(&mut self) -> Option<Vec<u8>> {
+    match &mut self.inner {
+      Inner::Full(bytes) if bytes.is_empty() => None,
+      Inner::Full(bytes) => Some(std::mem::take(bytes)),
+      Inner::Stream(rx) => loop {
+        match rx.recv().await? {
+          chunk if chunk.is_empty() => continue,
+          chunk => return Some(chunk),
+        }
+      },
+    }
+  }
+}
+impl fmt::Debug for Body {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.inner {
+      Inner::Full(bytes) => f.debug_tuple("Body").field(bytes).finish(),
+      Inner::Stream(_) => f.write_str("Body(<stream>)"),
+    }
+  }
+}
+
+/// The producing half of a streaming [`Body`].
+#[derive(Debug, Clone)]
+pub struct BodySender {
+  inner: mpsc::Sender<Vec<u8>>,
+}
+impl BodySender {
+  /// Queues a chunk, waiting while the buffer is full. Fails once the connection has gone away.
+  pub async fn send(&self, chunk: impl Into<Vec<u8>>) -> Result<(), HttpError> {
+    self
+      .inner
+      .send(chunk.into())
+      .await
+      .map_err(|_| HttpError::BodyClosed)
   }
 }
 impl From<Vec<u8>> for Body {
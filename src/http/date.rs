@@ -0,0 +1,198 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::HttpError;
+
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const MONTHS: [&str; 12] = [
+  "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A timestamp as used in `Date`, `Last-Modified` and friends, with one-second precision.
+/// Formats as IMF-fixdate and parses all three formats RFC 9110 (section 5.6.7) asks recipients
+/// to accept.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HttpDate {
+  secs: u64,
+}
+
+impl HttpDate {
+  pub fn now() -> Self {
+    SystemTime::now().into()
+  }
+  pub fn from_unix(secs: u64) -> Self {
+    Self { secs }
+  }
+  pub fn as_unix(&self) -> u64 {
+    self.secs
+  }
+}
+
+impl From<SystemTime> for HttpDate {
+  fn from(value: SystemTime) -> Self {
+    let secs = value
+      .duration_since(UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default();
+    Self { secs }
+  }
+}
+impl From<HttpDate> for SystemTime {
+  fn from(value: HttpDate) -> Self {
+    UNIX_EPOCH + Duration::from_secs(value.secs)
+  }
+}
+
+impl fmt::Display for HttpDate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let days = self.secs / 86400;
+    let secs = self.secs % 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+    // 1970-01-01 was a Thursday.
+    let weekday = DAYS[((days + 3) % 7) as usize];
+    write!(
+      f,
+      "{weekday}, {day:02} {} {year:04} {:02}:{:02}:{:02} GMT",
+      MONTHS[month as usize - 1],
+      secs / 3600,
+      secs / 60 % 60,
+      secs % 60
+    )
+  }
+}
+
+impl FromStr for HttpDate {
+  type Err = HttpError;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || HttpError::InvalidHeaderValue("date".into(), s.into());
+    let parts = s.split_ascii_whitespace().collect::<Vec<_>>();
+    let (day, month, year, time) = match parts[..] {
+      // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+      [_, day, month, year, time, "GMT"] => (day, month, year.parse().ok(), time),
+      // asctime: Sun Nov  6 08:49:37 1994
+      [_, month, day, time, year] => (day, month, year.parse().ok(), time),
+      // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+      [_, date, time, "GMT"] => {
+        let mut date = date.split('-');
+        let (Some(day), Some(month), Some(year)) = (date.next(), date.next(), date.next()) else {
+          return Err(invalid());
+        };
+        // Two-digit years that look more than 50 years ahead are in the past century.
+        let year = year
+          .parse::<i64>()
+          .ok()
+          .filter(|_| year.len() == 2)
+          .map(|y| if y < 70 { 2000 + y } else { 1900 + y });
+        (day, month, year, time)
+      }
+      _ => return Err(invalid()),
+    };
+    let day = day.parse::<u32>().ok();
+    let month = MONTHS
+      .iter()
+      .position(|m| *m == month)
+      .map(|m| m as u32 + 1);
+    let mut time = time.split(':').map(|t| t.parse::<u64>().ok());
+    let (Some(day), Some(month), Some(year), Some(Some(h)), Some(Some(m)), Some(Some(s)), None) = (
+      day,
+      month,
+      year,
+      time.next(),
+      time.next(),
+      time.next(),
+      time.next(),
+    ) else {
+      return Err(invalid());
+    };
+    // Four-digit years are all the formats can express, and keep the arithmetic in range.
+    if !(1970..=9999).contains(&year) || !(1..=days_in_month(year, month)).contains(&day) {
+      return Err(invalid());
+    }
+    if h > 23 || m > 59 || s > 60 {
+      return Err(invalid());
+    }
+    let days = u64::try_from(days_from_civil(year, month, day)).map_err(|_| invalid())?;
+    let secs = days
+      .checked_mul(86400)
+      .and_then(|secs| secs.checked_add(h * 3600 + m * 60 + s))
+      .ok_or_else(invalid)?;
+    Ok(Self { secs })
+  }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+  match month {
+    2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31,
+  }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date, after Howard Hinnant's algorithm.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let yoe = year - era * 400;
+  let mp = (month as i64 + 9) % 12;
+  let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+  era * 146097 + doe - 719468
+}
+
+/// The inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+  let z = days + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z - era * 146097;
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+  let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+  (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_every_format_and_formats_imf_fixdate() {
+    let expected = HttpDate::from_unix(784111777);
+    for s in [
+      "Sun, 06 Nov 1994 08:49:37 GMT",
+      "Sunday, 06-Nov-94 08:49:37 GMT",
+      "Sun Nov  6 08:49:37 1994",
+    ] {
+      assert_eq!(s.parse::<HttpDate>().unwrap(), expected, "{s}");
+    }
+    assert_eq!(expected.to_string(), "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(
+      HttpDate::from_unix(951782400).to_string(),
+      "Tue, 29 Feb 2000 00:00:00 GMT"
+    );
+    assert!("Sun, 06 Foo 1994 08:49:37 GMT".parse::<HttpDate>().is_err());
+  }
+
+  #[test]
+  fn out_of_range_dates_are_rejected() {
+    for s in [
+      "Sun, 06 Nov 999999999999 08:49:37 GMT",
+      "Sun Nov  6 08:49:37 -9223372036854775808",
+      "Sun, 06 Nov 10000 08:49:37 GMT",
+      "Sunday, 06-Nov-9223372036854775807 08:49:37 GMT",
+      "Sun, 06 Nov 1969 08:49:37 GMT",
+      "Fri, 31 Feb 2025 00:00:00 GMT",
+      "Sat, 29 Feb 2025 00:00:00 GMT",
+      "Mon, 31 Apr 2025 00:00:00 GMT",
+      "Sun, 00 Nov 1994 08:49:37 GMT",
+    ] {
+      assert!(s.parse::<HttpDate>().is_err(), "{s}");
+    }
+    assert!("Thu, 29 Feb 2024 00:00:00 GMT".parse::<HttpDate>().is_ok());
+    assert!("Fri, 31 Dec 9999 23:59:59 GMT".parse::<HttpDate>().is_ok());
+  }
+}
@@ -0,0 +1,153 @@
+use std::fmt;
+
+use super::HttpError;
+
+/// Header fields of a request or response. Names are compared without regard to ASCII case, a
+/// name can carry several values, and fields keep the order they were added in.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+  entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+  /// The first value of the field called `name`.
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self.position(name).map(|i| self.entries[i].1.as_str())
+  }
+  /// Every value of the field called `name`, in the order they were added.
+  pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    self
+      .entries
+      .iter()
+      .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+      .map(|(_, v)| v.as_str())
+  }
+  pub fn contains(&self, name: &str) -> bool {
+    self.get(name).is_some()
+  }
+  /// Sets the field to a single value, replacing any values it had. The field keeps the position
+  /// of its first occurrence. Returns the first of the replaced values.
+  pub fn insert(
+    &mut self,
+    name: impl Into<String>,
+    value: impl Into<String>,
+  ) -> Result<Option<String>, HttpError> {
+    let (name, value) = validate(name.into(), value.into())?;
+    let Some(i) = self.position(&name) else {
+      self.entries.push((name, value));
+      return Ok(None);
+    };
+    let previous = std::mem::replace(&mut self.entries[i].1, value);
+    let mut j = i + 1;
+    while j < self.entries.len() {
+      if self.entries[j].0.eq_ignore_ascii_case(&name) {
+        self.entries.remove(j);
+      } else {
+        j += 1;
+      }
+    }
+    Ok(Some(previous))
+  }
+  /// Adds a value to the field, keeping the values it already had.
+  pub fn append(
+    &mut self,
+    name: impl Into<String>,
+    value: impl Into<String>,
+  ) -> Result<(), HttpError> {
+    let entry = validate(name.into(), value.into())?;
+    self.entries.push(entry);
+    Ok(())
+  }
+  /// Removes every value of the field, returning the first one.
+  pub fn remove(&mut self, name: &str) -> Option<String> {
+    let i = self.position(name)?;
+    let (_, first) = self.entries.remove(i);
+    self.entries.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    Some(first)
+  }
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+  }
+  fn position(&self, name: &str) -> Option<usize> {
+    self
+      .entries
+      .iter()
+      .position(|(k, _)| k.eq_ignore_ascii_case(name))
+  }
+}
+
+impl fmt::Debug for HeaderMap {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_map().entries(self.iter()).finish()
+  }
+}
+
+/// Token characters, which make up methods, header names and many header values (RFC 9110,
+/// section 5.6.2).
+pub fn is_tchar(b: u8) -> bool {
+  b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+pub fn is_token(s: &str) -> bool {
+  !s.is_empty() && s.bytes().all(is_tchar)
+}
+
+/// Names must be tokens, and values may not contain control characters other than tab, which
+/// keeps a value from smuggling in extra header lines.
+fn validate(name: String, value: String) -> Result<(String, String), HttpError> {
+  if !is_token(&name) {
+    return Err(HttpError::InvalidHeader(name));
+  }
+  if value.bytes().any(|b| b.is_ascii_control() && b != b'\t') {
+    return Err(HttpError::InvalidHeaderValue(name, value));
+  }
+  Ok((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn names_are_case_insensitive_and_values_accumulate() {
+    let mut headers = HeaderMap::new();
+    headers.append("Set-Cookie", "a=1").unwrap();
+    headers.append("Content-Type", "text/plain").unwrap();
+    headers.append("set-cookie", "b=2").unwrap();
+    assert_eq!(headers.get("SET-COOKIE"), Some("a=1"));
+    assert_eq!(
+      headers.get_all("Set-Cookie").collect::<Vec<_>>(),
+      ["a=1", "b=2"]
+    );
+
+    assert_eq!(
+      headers.insert("set-cookie", "c=3").unwrap(),
+      Some("a=1".into())
+    );
+    assert_eq!(
+      headers.iter().collect::<Vec<_>>(),
+      [("Set-Cookie", "c=3"), ("Content-Type", "text/plain")]
+    );
+
+    assert_eq!(headers.remove("content-type"), Some("text/plain".into()));
+    assert_eq!(headers.len(), 1);
+  }
+
+  #[test]
+  fn invalid_names_and_values_are_rejected() {
+    let mut headers = HeaderMap::new();
+    assert!(headers.append("Bad Name", "x").is_err());
+    assert!(headers.append("", "x").is_err());
+    assert!(headers.insert("X-Split", "a\r\nInjected: 1").is_err());
+    assert!(headers.is_empty());
+  }
+}
@@ -1,21 +1,118 @@
-#[derive(Debug, Copy, Clone, PartialEq)]
+use std::fmt;
+
+use super::is_token;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RequestMethod {
   Get,
+  Head,
   Post,
   Put,
   Patch,
   Delete,
+  Options,
+  Connect,
+  Trace,
+  /// Any other method token, such as WebDAV's `PROPFIND`. Methods are case-sensitive, so the
+  /// token is kept exactly as received.
+  Extension(String),
+}
+impl RequestMethod {
+  pub fn as_str(&self) -> &str {
+    match self {
+      Self::Get => "GET",
+      Self::Head => "HEAD",
+      Self::Post => "POST",
+      Self::Put => "PUT",
+      Self::Patch => "PATCH",
+      Self::Delete => "DELETE",
+      Self::Options => "OPTIONS",
+      Self::Connect => "CONNECT",
+      Self::Trace => "TRACE",
+      Self::Extension(token) => token,
+    }
+  }
+  /// Safe methods are read-only (RFC 9110, section 9.2.1). Nothing is assumed about extension
+  /// methods.
+  pub fn is_safe(&self) -> bool {
+    matches!(self, Self::Get | Self::Head | Self::Options | Self::Trace)
+  }
+  /// Idempotent methods can be retried without changing the outcome (RFC 9110, section 9.2.2).
+  pub fn is_idempotent(&self) -> bool {
+    self.is_safe() || matches!(self, Self::Put | Self::Delete)
+  }
+}
+impl fmt::Display for RequestMethod {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
 }
 impl TryFrom<String> for RequestMethod {
   type Error = ();
   fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
     match value.as_str() {
       "GET" => Ok(RequestMethod::Get),
+      "HEAD" => Ok(RequestMethod::Head),
       "POST" => Ok(RequestMethod::Post),
       "PUT" => Ok(RequestMethod::Put),
       "DELETE" => Ok(RequestMethod::Delete),
       "PATCH" => Ok(RequestMethod::Patch),
+      "OPTIONS" => Ok(RequestMethod::Options),
+      "CONNECT" => Ok(RequestMethod::Connect),
+      "TRACE" => Ok(RequestMethod::Trace),
+      token if is_token(token) => Ok(RequestMethod::Extension(value)),
       _ => Err(()),
     }
   }
 }
+impl TryFrom<&str> for RequestMethod {
+  type Error = ();
+  fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
+    Self::try_from(value.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn methods_parse_and_classify() {
+    assert_eq!(RequestMethod::try_from("GET"), Ok(RequestMethod::Get));
+    assert_eq!(
+      RequestMethod::try_from("CONNECT"),
+      Ok(RequestMethod::Connect)
+    );
+    assert_eq!(
+      RequestMethod::try_from("PROPFIND"),
+      Ok(RequestMethod::Extension("PROPFIND".into()))
+    );
+    // Methods are case-sensitive, so this is an extension rather than GET.
+    assert_eq!(
+      RequestMethod::try_from("get"),
+      Ok(RequestMethod::Extension("get".into()))
+    );
+    for invalid in ["", "GE T", "G(T", "PROP\u{e9}"] {
+      assert_eq!(RequestMethod::try_from(invalid), Err(()), "{invalid}");
+    }
+
+    let safe = [
+      RequestMethod::Get,
+      RequestMethod::Head,
+      RequestMethod::Options,
+      RequestMethod::Trace,
+    ];
+    assert!(safe.iter().all(|m| m.is_safe() && m.is_idempotent()));
+    for method in [RequestMethod::Put, RequestMethod::Delete] {
+      assert!(!method.is_safe() && method.is_idempotent());
+    }
+    for method in [
+      RequestMethod::Post,
+      RequestMethod::Patch,
+      RequestMethod::Connect,
+      RequestMethod::Extension("PROPFIND".into()),
+    ] {
+      assert!(!method.is_safe() && !method.is_idempotent(), "{method}");
+    }
+  }
+}
@@ -10,11 +10,29 @@ pub enum HttpError {
   InvalidRequestLine(String),
   #[error("header of wrong type, {0} should not be {1}")]
   InvalidHeaderValue(String, String),
+  #[error("the receiving end of the body went away")]
+  BodyClosed,
+}
+impl From<std::convert::Infallible> for HttpError {
+  fn from(value: std::convert::Infallible) -> Self {
+    match value {}
+  }
 }
 
 mod common;
 pub use common::Body;
-pub use common::Headers;
+pub use common::BodySender;
+
+mod header;
+pub use header::is_tchar;
+pub use header::is_token;
+pub use header::HeaderMap;
+
+mod date;
+pub use date::HttpDate;
+
+pub mod typed;
+pub use typed::TypedHeader;
 
 mod uri;
 pub use uri::Uri;
@@ -22,9 +40,16 @@ pub use uri::Uri;
 mod code;
 pub use code::StatusCode;
 
+mod version;
+pub use version::Version;
+
 mod method;
 pub use method::RequestMethod;
 
+mod params;
+pub use params::Params;
+pub use params::Uuid;
+
 mod request;
 pub use request::Request;
 
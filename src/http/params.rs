@@ -0,0 +1,113 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::HttpError;
+
+/// Path parameters captured by the route that matched a request, e.g. `id` for `/users/:id`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+  entries: Vec<(String, String)>,
+}
+
+impl Params {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+  pub fn get(&self, name: &str) -> Option<&str> {
+    self
+      .entries
+      .iter()
+      .find(|(k, _)| k == name)
+      .map(|(_, v)| v.as_str())
+  }
+  /// Sets a parameter, replacing an earlier value with the same name.
+  pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+    let (name, value) = (name.into(), value.into());
+    match self.entries.iter_mut().find(|(k, _)| *k == name) {
+      Some(entry) => entry.1 = value,
+      None => self.entries.push((name, value)),
+    }
+  }
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+  }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Params {
+  fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+    let mut params = Self::new();
+    for (k, v) in iter {
+      params.insert(k, v);
+    }
+    params
+  }
+}
+
+/// A UUID in its hyphenated form, e.g. `67e55044-10b1-426f-9247-bb680e5fe0c8`. Parsing accepts
+/// either case; formatting is lowercase.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid {
+  pub fn from_bytes(bytes: [u8; 16]) -> Self {
+    Self(bytes)
+  }
+  pub fn as_bytes(&self) -> &[u8; 16] {
+    &self.0
+  }
+}
+
+impl FromStr for Uuid {
+  type Err = HttpError;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let invalid = || HttpError::InvalidHeaderValue("uuid".into(), s.into());
+    let groups = s.split('-').map(str::len).collect::<Vec<_>>();
+    if groups != [8, 4, 4, 4, 12] || !s.bytes().all(|b| b == b'-' || b.is_ascii_hexdigit()) {
+      return Err(invalid());
+    }
+    let nibble = |b: u8| (b as char).to_digit(16).unwrap() as u8;
+    let hex = s.bytes().filter(|b| *b != b'-').collect::<Vec<_>>();
+    let mut bytes = [0; 16];
+    for (byte, pair) in bytes.iter_mut().zip(hex.chunks(2)) {
+      *byte = nibble(pair[0]) << 4 | nibble(pair[1]);
+    }
+    Ok(Self(bytes))
+  }
+}
+
+impl fmt::Display for Uuid {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, byte) in self.0.iter().enumerate() {
+      if matches!(i, 4 | 6 | 8 | 10) {
+        f.write_str("-")?;
+      }
+      write!(f, "{byte:02x}")?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn uuids_round_trip() {
+    let s = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+    let uuid = s.to_uppercase().parse::<Uuid>().unwrap();
+    assert_eq!(uuid.to_string(), s);
+    assert!("67e55044-10b1-426f-9247".parse::<Uuid>().is_err());
+    assert!("67e55044-10b1-426f-9247-bb680e5fe0cg"
+      .parse::<Uuid>()
+      .is_err());
+    assert!("+7e55044-10b1-426f-9247-bb680e5fe0c8"
+      .parse::<Uuid>()
+      .is_err());
+  }
+}
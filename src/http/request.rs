@@ -1,20 +1,37 @@
+use std::str::FromStr;
+
 use super::*;
+use crate::error::{Error, Kind};
 
 #[derive(Debug)]
 pub struct Request {
   method: RequestMethod,
   uri: Uri,
-  headers: Headers,
+  version: Version,
+  headers: HeaderMap,
   body: Body,
+  trailers: HeaderMap,
+  params: Params,
+  prefix: String,
 }
 
 impl Request {
-  pub fn new(method: RequestMethod, uri: Uri, headers: Headers, body: Body) -> Self {
+  pub fn new(
+    method: RequestMethod,
+    uri: Uri,
+    version: Version,
+    headers: HeaderMap,
+    body: Body,
+  ) -> Self {
     Self {
       method,
       uri,
+      version,
       headers,
       body,
+      trailers: HeaderMap::new(),
+      params: Params::new(),
+      prefix: String::new(),
     }
   }
 
@@ -26,11 +43,86 @@ impl Request {
     &self.uri
   }
 
-  pub fn headers(&self) -> &Headers {
+  pub fn version(&self) -> Version {
+    self.version
+  }
+
+  pub fn headers(&self) -> &HeaderMap {
     &self.headers
   }
 
+  pub fn headers_mut(&mut self) -> &mut HeaderMap {
+    &mut self.headers
+  }
+
   pub fn body(&self) -> &Body {
     &self.body
   }
+
+  pub fn body_mut(&mut self) -> &mut Body {
+    &mut self.body
+  }
+
+  /// Trailer fields sent after a chunked body.
+  pub fn trailers(&self) -> &HeaderMap {
+    &self.trailers
+  }
+
+  pub fn trailers_mut(&mut self) -> &mut HeaderMap {
+    &mut self.trailers
+  }
+
+  /// Path parameters captured by the route that matched this request.
+  pub fn params(&self) -> &Params {
+    &self.params
+  }
+
+  pub fn params_mut(&mut self) -> &mut Params {
+    &mut self.params
+  }
+
+  /// The part of the path matched by the prefixes of the routers this request was nested under,
+  /// e.g. `/api/v1` for `/api/v1/users` routed through a router nested at `/api/v1`.
+  pub fn prefix(&self) -> &str {
+    &self.prefix
+  }
+
+  pub fn prefix_mut(&mut self) -> &mut String {
+    &mut self.prefix
+  }
+
+  /// The path with [`prefix`](Self::prefix) cut off, as the nested router matched it.
+  pub fn nested_path(&self) -> &str {
+    match &self.uri.path()[self.prefix.len()..] {
+      "" => "/",
+      path => path,
+    }
+  }
+
+  /// Parses a path parameter, e.g. `request.param::<u64>("id")`. A missing or unparsable
+  /// parameter is an error that turns into a 400 response.
+  pub fn param<T: FromStr>(&self, name: &str) -> Result<T, Error> {
+    self
+      .params
+      .get(name)
+      .and_then(|value| value.parse().ok())
+      .ok_or_else(|| Error::new(Kind::Param(name.into())))
+  }
+
+  /// Whether the client asked for the connection to stay open after this request.
+  pub fn keep_alive(&self) -> bool {
+    let tokens = self
+      .headers
+      .get_all("Connection")
+      .flat_map(|v| v.split(','))
+      .map(str::trim)
+      .collect::<Vec<_>>();
+    if tokens.iter().any(|t| t.eq_ignore_ascii_case("close")) {
+      false
+    } else if tokens.iter().any(|t| t.eq_ignore_ascii_case("keep-alive")) {
+      true
+    } else {
+      self.version.keep_alive_by_default()
+    }
+  }
 }
@@ -1,11 +1,9 @@
-use std::collections::HashMap;
-
 use super::*;
 
 #[derive(Debug, Default)]
 pub struct Response {
   status_code: StatusCode,
-  headers: Headers,
+  headers: HeaderMap,
   body: Body,
 }
 
@@ -13,7 +11,7 @@ impl Response {
   pub fn builder() -> ResponseBuilder {
     ResponseBuilder::new()
   }
-  pub fn new(status_code: StatusCode, headers: Headers, body: Body) -> Self {
+  pub fn new(status_code: StatusCode, headers: HeaderMap, body: Body) -> Self {
     Self {
       status_code,
       headers,
@@ -21,41 +19,52 @@ impl Response {
     }
   }
   pub fn from_plain_text(code: StatusCode, body: &str) -> Self {
-    Self {
-      status_code: code,
-      headers: Headers::from([("Content-Length".to_string(), body.len().to_string())]),
-      body: body.to_string().into(),
-    }
+    Self::new(code, HeaderMap::new(), body.into())
   }
   pub fn from_html_ok(html: &str) -> Self {
-    Self {
-      status_code: StatusCode::Ok,
-      headers: Headers::from([
-        ("Content-Length".to_string(), html.len().to_string()),
-        (
-          "Content-Type".to_string(),
-          "text/html; charset=utf-8".to_string(),
-        ),
-      ]),
-      body: html.to_string().into(),
+    Response::builder()
+      .header("Content-Type", "text/html; charset=utf-8")
+      .body(html)
+      .unwrap()
+  }
+  pub fn status_code(&self) -> &StatusCode {
+    &self.status_code
+  }
+  pub fn headers(&self) -> &HeaderMap {
+    &self.headers
+  }
+  pub fn headers_mut(&mut self) -> &mut HeaderMap {
+    &mut self.headers
+  }
+  pub fn body(&self) -> &Body {
+    &self.body
+  }
+  pub fn body_mut(&mut self) -> &mut Body {
+    &mut self.body
+  }
+  /// The status line and headers, including the empty line that ends them.
+  pub fn head_bytes(&self) -> Vec<u8> {
+    let mut head = format!("HTTP/1.1 {}\r\n", self.status_code);
+    for (k, v) in self.headers.iter() {
+      head.push_str(&format!("{k}: {v}\r\n"));
     }
+    head.push_str("\r\n");
+    head.into_bytes()
   }
+  /// Serializes a response with an in-memory body. A `Content-Length` header is added when the
+  /// handler didn't set one so the client can find the end of the body on a persistent
+  /// connection. Statuses that can't have content get neither. Streaming bodies are written by
+  /// the server instead.
   pub fn as_bytes(&self) -> Vec<u8> {
-    let status_line = format!("HTTP/1.1 {}\r\n", self.status_code.to_string());
-    let headers = self
-      .headers
-      .iter()
-      .map(|(k, v)| format!("{k}: {v}"))
-      .collect::<Vec<_>>()
-      .join("\r\n");
-    [
-      status_line.as_bytes(),
-      headers.as_bytes(),
-      b"\r\n\r\n",
-      self.body.get_bytes(),
-      b"\r\n",
-    ]
-    .concat()
+    let mut head = self.head_bytes();
+    if !self.status_code.allows_body() {
+      return head;
+    }
+    if !self.headers.contains("Content-Length") {
+      let len = format!("Content-Length: {}\r\n", self.body.get_bytes().len());
+      head.splice(head.len() - 2..head.len() - 2, len.into_bytes());
+    }
+    [&head[..], self.body.get_bytes()].concat()
   }
 }
 
@@ -82,13 +91,22 @@ impl ResponseBuilder {
   }
   pub fn header(self, key: &str, value: &str) -> Self {
     let inner = self.inner.and_then(|mut this| {
-      this.headers.insert(key.into(), value.into());
+      this.headers.append(key, value)?;
+      Ok(this)
+    });
+    Self { inner }
+  }
+  pub fn typed_header<T: TypedHeader>(self, header: T) -> Self {
+    let inner = self.inner.and_then(|mut this| {
+      this.headers.typed_insert(header)?;
       Ok(this)
     });
     Self { inner }
   }
   pub fn body(mut self, body: impl Into<Body>) -> Result<Response, HttpError> {
-    self.inner.as_mut().map(|mut this| this.body = body.into());
+    if let Ok(this) = self.inner.as_mut() {
+      this.body = body.into();
+    }
     self.inner
   }
 }
@@ -108,7 +126,7 @@ impl IntoResponse for String {
 }
 impl IntoResponse for Body {
   fn into_response(self) -> Response {
-    Response::new(StatusCode::Ok, Headers::new(), self)
+    Response::new(StatusCode::Ok, HeaderMap::new(), self)
   }
 }
 impl IntoResponse for Response {
@@ -0,0 +1,794 @@
+use std::fmt;
+
+use super::{is_token, HeaderMap, HttpDate, HttpError};
+
+/// A header with a structured value. Decoding and encoding live together so a value read off a
+/// request can be written back out unchanged.
+pub trait TypedHeader: Sized {
+  const NAME: &'static str;
+  /// Parses the field value. Fields sent on several lines arrive joined with `", "`.
+  fn decode(value: &str) -> Result<Self, HttpError>;
+  fn encode(&self) -> String;
+}
+
+impl HeaderMap {
+  /// Reads a typed header, or `None` when the request doesn't have it.
+  pub fn typed<T: TypedHeader>(&self) -> Result<Option<T>, HttpError> {
+    let values = self.get_all(T::NAME).collect::<Vec<_>>();
+    if values.is_empty() {
+      return Ok(None);
+    }
+    T::decode(&values.join(", ")).map(Some)
+  }
+  pub fn typed_insert<T: TypedHeader>(&mut self, header: T) -> Result<(), HttpError> {
+    self.insert(T::NAME, header.encode()).map(|_| ())
+  }
+}
+
+fn invalid(name: &str, value: &str) -> HttpError {
+  HttpError::InvalidHeaderValue(name.into(), value.into())
+}
+
+/// Splits a comma-separated list, leaving commas inside quoted strings alone. Empty elements
+/// are dropped.
+fn split_list(value: &str) -> Vec<&str> {
+  let mut items = split_unquoted(value, ',');
+  items.retain(|item| !item.is_empty());
+  items
+}
+
+/// Splits on `delimiter` where it isn't inside a quoted string, trimming each part.
+fn split_unquoted(value: &str, delimiter: char) -> Vec<&str> {
+  let mut items = vec![];
+  let mut quoted = false;
+  let mut escaped = false;
+  let mut start = 0;
+  for (i, c) in value.char_indices() {
+    match c {
+      _ if escaped => escaped = false,
+      '\\' if quoted => escaped = true,
+      '"' => quoted = !quoted,
+      c if c == delimiter && !quoted => {
+        items.push(value[start..i].trim());
+        start = i + 1;
+      }
+      _ => {}
+    }
+  }
+  items.push(value[start..].trim());
+  items
+}
+
+/// Reads a parameter value, which is either a token or a quoted string.
+fn unquote(value: &str) -> Option<String> {
+  let Some(inner) = value.strip_prefix('"') else {
+    return is_token(value).then(|| value.to_string());
+  };
+  let inner = inner.strip_suffix('"')?;
+  let mut out = String::new();
+  let mut chars = inner.chars();
+  while let Some(c) = chars.next() {
+    match c {
+      '\\' => out.push(chars.next()?),
+      '"' => return None,
+      c => out.push(c),
+    }
+  }
+  Some(out)
+}
+
+fn quote(value: &str) -> String {
+  if is_token(value) {
+    value.to_string()
+  } else {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+  }
+}
+
+/// A media type such as `text/html; charset=utf-8`. The type, subtype and parameter names are
+/// case-insensitive and stored lowercase; parameter values keep their case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaType {
+  type_: String,
+  subtype: String,
+  params: Vec<(String, String)>,
+}
+
+impl MediaType {
+  pub fn new(type_: &str, subtype: &str) -> Self {
+    Self {
+      type_: type_.to_ascii_lowercase(),
+      subtype: subtype.to_ascii_lowercase(),
+      params: vec![],
+    }
+  }
+  pub fn with_param(mut self, name: &str, value: &str) -> Self {
+    self.params.push((name.to_ascii_lowercase(), value.into()));
+    self
+  }
+  pub fn type_(&self) -> &str {
+    &self.type_
+  }
+  pub fn subtype(&self) -> &str {
+    &self.subtype
+  }
+  /// The type and subtype without parameters, e.g. `text/html`.
+  pub fn essence(&self) -> String {
+    format!("{}/{}", self.type_, self.subtype)
+  }
+  pub fn param(&self, name: &str) -> Option<&str> {
+    self
+      .params
+      .iter()
+      .find(|(k, _)| k.eq_ignore_ascii_case(name))
+      .map(|(_, v)| v.as_str())
+  }
+  pub fn charset(&self) -> Option<&str> {
+    self.param("charset")
+  }
+  /// Whether this type, possibly a range like `text/*`, covers `other`. Parameters on the
+  /// range have to be on `other` too, with the same value.
+  pub fn matches(&self, other: &MediaType) -> bool {
+    (self.type_ == "*" || self.type_ == other.type_)
+      && (self.subtype == "*" || self.subtype == other.subtype)
+      && self.params.iter().all(|(name, value)| {
+        other.param(name).is_some_and(|v| match name.as_str() {
+          "charset" => v.eq_ignore_ascii_case(value),
+          _ => v == value,
+        })
+      })
+  }
+  fn parse(value: &str) -> Option<Self> {
+    let mut parts = split_unquoted(value, ';').into_iter();
+    let (type_, subtype) = parts.next()?.split_once('/')?;
+    let (type_, subtype) = (type_.trim(), subtype.trim());
+    if !is_token(type_) || !is_token(subtype) {
+      return None;
+    }
+    let mut media = Self::new(type_, subtype);
+    for param in parts {
+      let (name, value) = param.split_once('=')?;
+      let name = name.trim();
+      if !is_token(name) {
+        return None;
+      }
+      media = media.with_param(name, &unquote(value.trim())?);
+    }
+    Some(media)
+  }
+}
+
+impl fmt::Display for MediaType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}/{}", self.type_, self.subtype)?;
+    for (name, value) in &self.params {
+      write!(f, "; {name}={}", quote(value))?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType(pub MediaType);
+impl TypedHeader for ContentType {
+  const NAME: &'static str = "Content-Type";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    MediaType::parse(value)
+      .map(Self)
+      .ok_or_else(|| invalid(Self::NAME, value))
+  }
+  fn encode(&self) -> String {
+    self.0.to_string()
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ContentLength(pub u64);
+impl ContentLength {
+  /// Whether the header holds a well-formed length too large for a `u64`, which `decode`
+  /// rejects like any other invalid value.
+  pub(crate) fn overflows(headers: &HeaderMap) -> bool {
+    let value = headers.get_all(Self::NAME).collect::<Vec<_>>().join(", ");
+    Self::digits(&value).is_some_and(|digits| digits.parse::<u64>().is_err())
+  }
+  /// The digits of the length. Repeated identical values are allowed (RFC 9110, section 8.6),
+  /// but an empty element is no length at all rather than one to skip.
+  fn digits(value: &str) -> Option<&str> {
+    let mut values = value.split(',').map(str::trim);
+    let first = values.next()?;
+    let digits = !first.is_empty() && first.bytes().all(|b| b.is_ascii_digit());
+    (digits && values.all(|v| v == first)).then_some(first)
+  }
+}
+impl TypedHeader for ContentLength {
+  const NAME: &'static str = "Content-Length";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    Self::digits(value)
+      .and_then(|digits| digits.parse().ok())
+      .map(Self)
+      .ok_or_else(|| invalid(Self::NAME, value))
+  }
+  fn encode(&self) -> String {
+    self.0.to_string()
+  }
+}
+
+/// A preference weight between 0 and 1000, i.e. `q=0.5` is 500.
+pub type Quality = u16;
+
+fn parse_quality(value: &str) -> Option<Quality> {
+  let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+  if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+  let frac = format!("{frac:0<3}").parse::<u16>().ok()?;
+  match int {
+    "0" => Some(frac),
+    "1" if frac == 0 => Some(1000),
+    _ => None,
+  }
+}
+
+/// The media ranges a client accepts, most preferred first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accept(pub Vec<(MediaType, Quality)>);
+impl Accept {
+  /// The quality the client assigns to `media`, taken from the most specific matching range:
+  /// exact types beat `type/*`, which beats `*/*`, and more parameters beat fewer. Equally
+  /// specific ranges give the highest quality among them.
+  pub fn quality_of(&self, media: &MediaType) -> Quality {
+    self
+      .0
+      .iter()
+      .filter(|(range, _)| range.matches(media))
+      .max_by_key(|(range, q)| {
+        let exact = (range.type_ != "*") as u8 + (range.subtype != "*") as u8;
+        (exact, range.params.len(), *q)
+      })
+      .map_or(0, |(_, q)| *q)
+  }
+  /// Picks the offer the client likes best, preferring earlier offers on ties.
+  pub fn negotiate<'a>(&self, offers: &'a [MediaType]) -> Option<&'a MediaType> {
+    offers
+      .iter()
+      .map(|offer| (offer, self.quality_of(offer)))
+      .filter(|(_, q)| *q > 0)
+      .fold(
+        None,
+        |best: Option<(&MediaType, Quality)>, (offer, q)| match best {
+          Some((_, best_q)) if best_q >= q => best,
+          _ => Some((offer, q)),
+        },
+      )
+      .map(|(offer, _)| offer)
+  }
+}
+impl TypedHeader for Accept {
+  const NAME: &'static str = "Accept";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    let mut ranges = vec![];
+    for item in split_list(value) {
+      let mut media = MediaType::parse(item).ok_or_else(|| invalid(Self::NAME, value))?;
+      let q = match media.params.iter().position(|(k, _)| k == "q") {
+        Some(i) => {
+          // Parameters after q are accept-ext, not part of the media range.
+          let (_, q) = media.params.remove(i);
+          media.params.truncate(i);
+          parse_quality(&q).ok_or_else(|| invalid(Self::NAME, value))?
+        }
+        None => 1000,
+      };
+      ranges.push((media, q));
+    }
+    ranges.sort_by_key(|(_, q)| std::cmp::Reverse(*q));
+    Ok(Self(ranges))
+  }
+  fn encode(&self) -> String {
+    self
+      .0
+      .iter()
+      .map(|(media, q)| match q {
+        1000 => media.to_string(),
+        q => format!("{media}; q={}", format!("0.{q:03}").trim_end_matches('0')),
+      })
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+}
+
+/// Cache directives, e.g. `no-cache` or `max-age=60`, in the order they were given.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl(pub Vec<(String, Option<String>)>);
+impl CacheControl {
+  pub fn new() -> Self {
+    Self::default()
+  }
+  pub fn with(mut self, directive: &str, value: Option<&str>) -> Self {
+    self
+      .0
+      .push((directive.to_ascii_lowercase(), value.map(str::to_string)));
+    self
+  }
+  pub fn has(&self, directive: &str) -> bool {
+    self
+      .0
+      .iter()
+      .any(|(d, _)| d.eq_ignore_ascii_case(directive))
+  }
+  pub fn get(&self, directive: &str) -> Option<&str> {
+    self
+      .0
+      .iter()
+      .find(|(d, _)| d.eq_ignore_ascii_case(directive))
+      .and_then(|(_, v)| v.as_deref())
+  }
+  pub fn max_age(&self) -> Option<u64> {
+    self.get("max-age")?.parse().ok()
+  }
+  pub fn no_cache(&self) -> bool {
+    self.has("no-cache")
+  }
+  pub fn no_store(&self) -> bool {
+    self.has("no-store")
+  }
+}
+impl TypedHeader for CacheControl {
+  const NAME: &'static str = "Cache-Control";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    let mut directives = Self::new();
+    for item in split_list(value) {
+      let (name, arg) = match item.split_once('=') {
+        Some((name, arg)) => (
+          name.trim(),
+          Some(unquote(arg.trim()).ok_or_else(|| invalid(Self::NAME, value))?),
+        ),
+        None => (item, None),
+      };
+      if !is_token(name) {
+        return Err(invalid(Self::NAME, value));
+      }
+      directives = directives.with(name, arg.as_deref());
+    }
+    Ok(directives)
+  }
+  fn encode(&self) -> String {
+    self
+      .0
+      .iter()
+      .map(|(d, v)| match v {
+        Some(v) => format!("{d}={}", quote(v)),
+        None => d.clone(),
+      })
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+}
+
+/// Credentials in the form `<scheme> <credentials>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authorization {
+  pub scheme: String,
+  pub credentials: String,
+}
+impl Authorization {
+  pub fn basic(user: &str, password: &str) -> Self {
+    Self {
+      scheme: "Basic".into(),
+      credentials: base64_encode(format!("{user}:{password}").as_bytes()),
+    }
+  }
+  pub fn bearer(token: &str) -> Self {
+    Self {
+      scheme: "Bearer".into(),
+      credentials: token.into(),
+    }
+  }
+  /// The user and password of `Basic` credentials.
+  pub fn as_basic(&self) -> Option<(String, String)> {
+    if !self.scheme.eq_ignore_ascii_case("Basic") {
+      return None;
+    }
+    let decoded = String::from_utf8(base64_decode(&self.credentials)?).ok()?;
+    let (user, password) = decoded.split_once(':')?;
+    Some((user.into(), password.into()))
+  }
+  pub fn as_bearer(&self) -> Option<&str> {
+    self
+      .scheme
+      .eq_ignore_ascii_case("Bearer")
+      .then_some(self.credentials.as_str())
+  }
+}
+impl TypedHeader for Authorization {
+  const NAME: &'static str = "Authorization";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    let (scheme, credentials) = value.trim().split_once(' ').unwrap_or((value.trim(), ""));
+    if !is_token(scheme) {
+      return Err(invalid(Self::NAME, value));
+    }
+    Ok(Self {
+      scheme: scheme.into(),
+      credentials: credentials.trim().into(),
+    })
+  }
+  fn encode(&self) -> String {
+    if self.credentials.is_empty() {
+      self.scheme.clone()
+    } else {
+      format!("{} {}", self.scheme, self.credentials)
+    }
+  }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(input: &[u8]) -> String {
+  let mut out = String::new();
+  for chunk in input.chunks(3) {
+    let b = [
+      chunk[0],
+      *chunk.get(1).unwrap_or(&0),
+      *chunk.get(2).unwrap_or(&0),
+    ];
+    let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+    for i in 0..4 {
+      if i <= chunk.len() {
+        out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+      } else {
+        out.push('=');
+      }
+    }
+  }
+  out
+}
+
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+  let input = input.trim_end_matches('=');
+  let mut out = vec![];
+  let mut n = 0u32;
+  for (i, c) in input.bytes().enumerate() {
+    n = n << 6 | BASE64.iter().position(|b| *b == c)? as u32;
+    if i % 4 == 3 {
+      out.extend_from_slice(&[(n >> 16) as u8, (n >> 8) as u8, n as u8]);
+    }
+  }
+  match input.len() % 4 {
+    0 => {}
+    2 => out.push((n >> 4) as u8),
+    3 => out.extend_from_slice(&[(n >> 10) as u8, (n >> 2) as u8]),
+    _ => return None,
+  }
+  Some(out)
+}
+
+/// The host and optional port the request is addressed to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+  pub host: String,
+  pub port: Option<u16>,
+}
+impl TypedHeader for Host {
+  const NAME: &'static str = "Host";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    let err = || invalid(Self::NAME, value);
+    let value = value.trim();
+    // IPv6 literals keep their brackets so the colons inside aren't mistaken for a port.
+    let split = match value.rfind(']') {
+      Some(end) => value[end..].find(':').map(|i| end + i),
+      None => value.rfind(':'),
+    };
+    let (host, port) = match split {
+      Some(i) => (
+        &value[..i],
+        Some(value[i + 1..].parse().map_err(|_| err())?),
+      ),
+      None => (value, None),
+    };
+    let valid = |b: u8| b.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:[]%".contains(&b);
+    if host.is_empty() || !host.bytes().all(valid) {
+      return Err(err());
+    }
+    Ok(Self {
+      host: host.to_ascii_lowercase(),
+      port,
+    })
+  }
+  fn encode(&self) -> String {
+    match self.port {
+      Some(port) => format!("{}:{port}", self.host),
+      None => self.host.clone(),
+    }
+  }
+}
+
+/// An entity tag, e.g. `"xyzzy"` or `W/"xyzzy"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntityTag {
+  pub weak: bool,
+  pub tag: String,
+}
+impl EntityTag {
+  pub fn strong(tag: &str) -> Self {
+    Self {
+      weak: false,
+      tag: tag.into(),
+    }
+  }
+  pub fn weak(tag: &str) -> Self {
+    Self {
+      weak: true,
+      tag: tag.into(),
+    }
+  }
+  /// Weak comparison, which is what `If-None-Match` uses (RFC 9110, section 8.8.3.2).
+  pub fn weak_eq(&self, other: &EntityTag) -> bool {
+    self.tag == other.tag
+  }
+  fn parse(value: &str) -> Option<Self> {
+    let (weak, rest) = match value.strip_prefix("W/") {
+      Some(rest) => (true, rest),
+      None => (false, value),
+    };
+    let tag = rest.strip_prefix('"')?.strip_suffix('"')?;
+    if tag.contains('"') {
+      return None;
+    }
+    Some(Self {
+      weak,
+      tag: tag.into(),
+    })
+  }
+}
+impl fmt::Display for EntityTag {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let prefix = if self.weak { "W/" } else { "" };
+    write!(f, "{prefix}\"{}\"", self.tag)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IfNoneMatch {
+  Any,
+  Tags(Vec<EntityTag>),
+}
+impl IfNoneMatch {
+  /// Whether the condition fails for a resource with the given tag, meaning the client's copy is
+  /// still current.
+  pub fn matches(&self, current: &EntityTag) -> bool {
+    match self {
+      Self::Any => true,
+      Self::Tags(tags) => tags.iter().any(|tag| tag.weak_eq(current)),
+    }
+  }
+}
+impl TypedHeader for IfNoneMatch {
+  const NAME: &'static str = "If-None-Match";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    if value.trim() == "*" {
+      return Ok(Self::Any);
+    }
+    split_list(value)
+      .into_iter()
+      .map(EntityTag::parse)
+      .collect::<Option<Vec<_>>>()
+      .map(Self::Tags)
+      .ok_or_else(|| invalid(Self::NAME, value))
+  }
+  fn encode(&self) -> String {
+    match self {
+      Self::Any => "*".into(),
+      Self::Tags(tags) => tags
+        .iter()
+        .map(EntityTag::to_string)
+        .collect::<Vec<_>>()
+        .join(", "),
+    }
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IfModifiedSince(pub HttpDate);
+impl TypedHeader for IfModifiedSince {
+  const NAME: &'static str = "If-Modified-Since";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    value
+      .trim()
+      .parse()
+      .map(Self)
+      .map_err(|_| invalid(Self::NAME, value))
+  }
+  fn encode(&self) -> String {
+    self.0.to_string()
+  }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Date(pub HttpDate);
+impl TypedHeader for Date {
+  const NAME: &'static str = "Date";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    value
+      .trim()
+      .parse()
+      .map(Self)
+      .map_err(|_| invalid(Self::NAME, value))
+  }
+  fn encode(&self) -> String {
+    self.0.to_string()
+  }
+}
+
+/// One range of a `Range: bytes=...` request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ByteRange {
+  /// `first-last`, both inclusive.
+  FromTo(u64, u64),
+  /// `first-`, up to the end.
+  From(u64),
+  /// `-n`, the final n bytes.
+  Last(u64),
+}
+impl ByteRange {
+  /// The inclusive byte positions this range covers in a representation of `len` bytes, or
+  /// `None` if it is unsatisfiable.
+  pub fn resolve(&self, len: u64) -> Option<(u64, u64)> {
+    match *self {
+      Self::FromTo(first, _) | Self::From(first) if first >= len => None,
+      Self::FromTo(first, last) => Some((first, last.min(len - 1))),
+      Self::From(first) => Some((first, len - 1)),
+      Self::Last(0) => None,
+      Self::Last(n) => Some((len.saturating_sub(n), len.checked_sub(1)?)),
+    }
+  }
+}
+impl fmt::Display for ByteRange {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::FromTo(first, last) => write!(f, "{first}-{last}"),
+      Self::From(first) => write!(f, "{first}-"),
+      Self::Last(n) => write!(f, "-{n}"),
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Range(pub Vec<ByteRange>);
+impl TypedHeader for Range {
+  const NAME: &'static str = "Range";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    let err = || invalid(Self::NAME, value);
+    let (unit, ranges) = value.trim().split_once('=').ok_or_else(err)?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+      return Err(err());
+    }
+    let mut out = vec![];
+    for range in split_list(ranges) {
+      let (first, last) = range.split_once('-').ok_or_else(err)?;
+      let num = |s: &str| s.trim().parse::<u64>().map_err(|_| err());
+      out.push(match (first.trim(), last.trim()) {
+        ("", last) => ByteRange::Last(num(last)?),
+        (first, "") => ByteRange::From(num(first)?),
+        (first, last) => {
+          let (first, last) = (num(first)?, num(last)?);
+          if last < first {
+            return Err(err());
+          }
+          ByteRange::FromTo(first, last)
+        }
+      });
+    }
+    if out.is_empty() {
+      return Err(err());
+    }
+    Ok(Self(out))
+  }
+  fn encode(&self) -> String {
+    let ranges = self.0.iter().map(ByteRange::to_string).collect::<Vec<_>>();
+    format!("bytes={}", ranges.join(", "))
+  }
+}
+
+/// Where a redirect or a newly created resource lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location(pub String);
+impl TypedHeader for Location {
+  const NAME: &'static str = "Location";
+  fn decode(value: &str) -> Result<Self, HttpError> {
+    let value = value.trim();
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_graphic()) {
+      return Err(invalid(Self::NAME, value));
+    }
+    Ok(Self(value.into()))
+  }
+  fn encode(&self) -> String {
+    self.0.clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn round_trip<T: TypedHeader + fmt::Debug + PartialEq>(value: &str) -> T {
+    let header = T::decode(value).unwrap();
+    assert_eq!(T::decode(&header.encode()).unwrap(), header);
+    header
+  }
+
+  #[test]
+  fn content_type_and_accept() {
+    let ContentType(media) = round_trip("Text/HTML; Charset=\"utf-8\"; x=\"a b\"");
+    assert_eq!(media.essence(), "text/html");
+    assert_eq!(media.charset(), Some("utf-8"));
+    assert_eq!(media.to_string(), "text/html; charset=utf-8; x=\"a b\"");
+
+    let accept: Accept = round_trip("text/*;q=0.3, text/html;q=0.7, text/html;level=1, */*;q=0.5");
+    assert_eq!(accept.0[0].0.to_string(), "text/html; level=1");
+    assert_eq!(accept.quality_of(&MediaType::new("text", "plain")), 300);
+    assert_eq!(accept.quality_of(&MediaType::new("image", "png")), 500);
+    assert_eq!(accept.quality_of(&MediaType::new("text", "html")), 700);
+    let level = MediaType::new("text", "html").with_param("level", "1");
+    assert_eq!(accept.quality_of(&level), 1000);
+
+    let accept = Accept::decode("text/html;level=1;q=0.2, text/html;q=0.7").unwrap();
+    assert_eq!(accept.quality_of(&MediaType::new("text", "html")), 700);
+    assert_eq!(accept.quality_of(&level), 200);
+    let accept = Accept::decode("text/html;q=0.2, text/html;q=0.7").unwrap();
+    assert_eq!(accept.quality_of(&MediaType::new("text", "html")), 700);
+    let offers = [
+      MediaType::new("application", "json"),
+      MediaType::new("text", "html"),
+    ];
+    assert_eq!(accept.negotiate(&offers), Some(&offers[1]));
+    assert!(Accept::decode("text/html;q=2").is_err());
+  }
+
+  #[test]
+  fn conditional_and_range_headers() {
+    let tags: IfNoneMatch = round_trip("W/\"a\", \"b\"");
+    assert!(tags.matches(&EntityTag::strong("a")));
+    assert!(!tags.matches(&EntityTag::strong("c")));
+
+    let Range(ranges) = round_trip("bytes=0-499, 500-, -200");
+    assert_eq!(ranges[0].resolve(300), Some((0, 299)));
+    assert_eq!(ranges[1].resolve(300), None);
+    assert_eq!(ranges[2].resolve(300), Some((100, 299)));
+
+    let since: IfModifiedSince = round_trip("Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(since.0.as_unix(), 784111777);
+  }
+
+  #[test]
+  fn other_headers() {
+    let auth = Authorization::basic("Aladdin", "open sesame");
+    assert_eq!(auth.encode(), "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+    let auth: Authorization = round_trip(&auth.encode());
+    assert_eq!(
+      auth.as_basic(),
+      Some(("Aladdin".into(), "open sesame".into()))
+    );
+
+    let host: Host = round_trip("[::1]:8080");
+    assert_eq!((host.host.as_str(), host.port), ("[::1]", Some(8080)));
+
+    let cache: CacheControl = round_trip("no-cache, max-age=60, private=\"Set-Cookie, X\"");
+    assert!(cache.no_cache());
+    assert_eq!(cache.max_age(), Some(60));
+    assert_eq!(cache.get("private"), Some("Set-Cookie, X"));
+
+    let mut headers = HeaderMap::new();
+    headers.append("Content-Length", "42").unwrap();
+    assert_eq!(
+      headers.typed::<ContentLength>().unwrap(),
+      Some(ContentLength(42))
+    );
+    headers
+      .insert("Content-Length", "18446744073709551616")
+      .unwrap();
+    assert!(headers.typed::<ContentLength>().is_err());
+    assert!(ContentLength::overflows(&headers));
+    headers.typed_insert(Location("/next".into())).unwrap();
+    assert_eq!(headers.get("location"), Some("/next"));
+    assert_eq!(headers.typed::<Date>().unwrap(), None);
+  }
+}
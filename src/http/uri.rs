@@ -1,7 +1,7 @@
 #[derive(Debug, Clone)]
 pub struct Uri {
   string: String,
-  query: Option<u16>,
+  query: Option<usize>,
 }
 impl Uri {
   pub fn from_parts(path: &str, query: &str) -> Self {
@@ -10,16 +10,16 @@ impl Uri {
     let query = if query.is_empty() {
       None
     } else {
-      Some(path.len() as u16)
+      Some(path.len())
     };
     Self { string, query }
   }
   pub fn from_str(src: &str) -> Self {
     let mut fragment = None;
     let mut query = None;
-    for (i, char) in src.chars().enumerate() {
+    for (i, char) in src.char_indices() {
       if char == '?' {
-        query = Some(i as u16);
+        query = Some(i);
       }
       if char == '#' {
         fragment = Some(i);
@@ -34,13 +34,13 @@ impl Uri {
   pub fn path(&self) -> &str {
     self
       .query
-      .and_then(|i| Some(&self.string[..i as usize]))
+      .map(|i| &self.string[..i])
       .unwrap_or(&self.string)
   }
   pub fn query(&self) -> &str {
     self
       .query
-      .and_then(|i| Some(&self.string[(i + 1) as usize..]))
+      .map(|i| &self.string[i + 1..])
       .unwrap_or_default()
   }
 }
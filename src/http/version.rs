@@ -0,0 +1,24 @@
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum Version {
+  Http10,
+  #[default]
+  Http11,
+}
+
+impl Version {
+  /// Whether a connection using this version stays open unless told otherwise.
+  pub fn keep_alive_by_default(&self) -> bool {
+    matches!(self, Self::Http11)
+  }
+}
+
+impl fmt::Display for Version {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::Http10 => "HTTP/1.0",
+      Self::Http11 => "HTTP/1.1",
+    })
+  }
+}
@@ -26,10 +26,20 @@ async fn handler(req: Request) -> Response {
 }
 #[tokio::main]
 async fn main() {
-  let router = Router::builder(not_found).get("/", handler).build();
+  let router = Router::builder(not_found)
+    .get("/", handler)
+    .build()
+    .expect("invalid routes");
   let my_handler = RouterHandler::new(router);
-  Server::new("127.0.0.1:8000")
+  let shutdown = Server::builder()
+    .bind("127.0.0.1:8000")
+    .build()
+    .expect("couldn't start the server")
     .listen(my_handler)
     .await
     .expect("error");
+  println!(
+    "shut down: {} connections drained, {} aborted",
+    shutdown.drained, shutdown.aborted
+  );
 }
@@ -0,0 +1,461 @@
+use crate::error::*;
+use crate::http::typed::ContentLength;
+use crate::http::{is_tchar, HeaderMap, Request, RequestMethod, Uri, Version};
+
+mod chunked;
+pub use chunked::ChunkedDecoder;
+
+/// How the length of a request body is determined.
+#[derive(Debug, PartialEq)]
+pub enum Framing {
+  Length(usize),
+  Chunked,
+}
+impl Framing {
+  /// Works out the body framing from the request headers. A request carrying both
+  /// `Content-Length` and `Transfer-Encoding` is rejected outright: intermediaries may disagree
+  /// on which one wins, which is how requests get smuggled.
+  pub fn of(request: &Request) -> Result<Self, Error> {
+    let headers = request.headers();
+    let codings = list(headers, "Transfer-Encoding");
+    // Lengths that overflow can never fit under the configured limit anyway.
+    let length = match headers.typed::<ContentLength>() {
+      Ok(length) => length.map(|ContentLength(len)| len.try_into().unwrap_or(usize::MAX)),
+      Err(_) if ContentLength::overflows(headers) => Some(usize::MAX),
+      Err(_) => return Err(Parse::ContentLength.into()),
+    };
+    match (&codings[..], length) {
+      ([], None) => Ok(Self::Length(0)),
+      ([], Some(len)) => Ok(Self::Length(len)),
+      // Chunked is the only coding we can decode, so it has to be the only one applied.
+      ([coding], None) if coding.eq_ignore_ascii_case("chunked") => Ok(Self::Chunked),
+      _ => Err(Parse::TransferEncoding.into()),
+    }
+  }
+}
+
+/// The non-empty elements of a comma-separated field, across every line it was sent on.
+fn list<'a>(headers: &'a HeaderMap, name: &'a str) -> Vec<&'a str> {
+  headers
+    .get_all(name)
+    .flat_map(|v| v.split(','))
+    .map(str::trim)
+    .filter(|v| !v.is_empty())
+    .collect()
+}
+
+pub fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Upper bounds on the parts of a request head.
+#[derive(Debug, Clone)]
+pub struct Limits {
+  pub max_request_line: usize,
+  pub max_headers: usize,
+  pub max_header_size: usize,
+}
+impl Default for Limits {
+  fn default() -> Self {
+    Self {
+      max_request_line: 8 * 1024,
+      max_headers: 100,
+      max_header_size: 8 * 1024,
+    }
+  }
+}
+
+/// The outcome of feeding input to a [`Parser`].
+#[derive(Debug)]
+pub enum Status<T> {
+  Complete(T),
+  /// The input ended before the message did; call again once more bytes have arrived.
+  Partial,
+}
+
+#[derive(Debug)]
+enum State {
+  RequestLine,
+  Headers(RequestMethod, Uri, Version),
+}
+
+/// Parses a request head. Input can arrive in pieces: every call consumes the complete lines
+/// at the front of the buffer and remembers where it got to, so nothing is parsed twice.
+#[derive(Debug)]
+pub struct Parser {
+  state: State,
+  headers: HeaderMap,
+  header_count: usize,
+  /// Bytes consumed by earlier calls, so errors can point into the whole request.
+  offset: usize,
+  limits: Limits,
+}
+
+impl Parser {
+  pub fn new(limits: Limits) -> Self {
+    Self {
+      state: State::RequestLine,
+      headers: HeaderMap::new(),
+      header_count: 0,
+      offset: 0,
+      limits,
+    }
+  }
+  /// Parses as much of `buf` as possible, draining the bytes it used. Once the head is complete
+  /// the rest of `buf` (the body, or the next pipelined request) is left untouched.
+  pub fn parse(&mut self, buf: &mut Vec<u8>) -> Result<Status<Request>, Error> {
+    let mut pos = 0;
+    let result = loop {
+      let rest = &buf[pos..];
+      let Some(end) = find(rest, b"\r\n") else {
+        if let Err(e) = self.check_line_length(rest.len(), self.offset + pos) {
+          break Err(e);
+        }
+        break Ok(Status::Partial);
+      };
+      if let Err(e) = self.check_line_length(end, self.offset + pos) {
+        break Err(e);
+      }
+      let line = Line::new(&rest[..end], self.offset + pos);
+      pos += end + 2;
+      match self.line(line) {
+        Ok(Some(request)) => break Ok(Status::Complete(request)),
+        Ok(None) => continue,
+        Err(e) => break Err(e),
+      }
+    };
+    buf.drain(..pos);
+    self.offset += pos;
+    result
+  }
+  fn check_line_length(&self, len: usize, at: usize) -> Result<(), Error> {
+    match self.state {
+      State::RequestLine if len > self.limits.max_request_line => {
+        Err(Parse::RequestLineTooLong(at).into())
+      }
+      State::Headers(..) if len > self.limits.max_header_size => {
+        Err(Parse::HeaderTooLarge(at).into())
+      }
+      _ => Ok(()),
+    }
+  }
+  fn line(&mut self, line: Line) -> Result<Option<Request>, Error> {
+    match std::mem::replace(&mut self.state, State::RequestLine) {
+      // Clients may send stray empty lines between requests.
+      State::RequestLine if line.is_empty() => Ok(None),
+      State::RequestLine => {
+        let (method, uri, version) = request_line(line)?;
+        self.state = State::Headers(method, uri, version);
+        Ok(None)
+      }
+      State::Headers(method, uri, version) if line.is_empty() => {
+        let headers = std::mem::take(&mut self.headers);
+        Ok(Some(Request::new(
+          method,
+          uri,
+          version,
+          headers,
+          Vec::new().into(),
+        )))
+      }
+      State::Headers(method, uri, version) => {
+        self.header_count += 1;
+        if self.header_count > self.limits.max_headers {
+          return Err(Parse::TooManyHeaders.into());
+        }
+        let at = line.offset();
+        let (name, value) = header(line)?;
+        self
+          .headers
+          .append(name, value)
+          .map_err(|_| Parse::Header(at))?;
+        self.state = State::Headers(method, uri, version);
+        Ok(None)
+      }
+    }
+  }
+}
+
+fn request_line(mut line: Line) -> Result<(RequestMethod, Uri, Version), Error> {
+  let at = line.offset();
+  let method = line.take_while(is_tchar);
+  let method = RequestMethod::try_from(String::from_utf8_lossy(method).into_owned())
+    .map_err(|_| Parse::Method(at))?;
+  let at = line.offset();
+  if line.next() != Some(b' ') {
+    return Err(Parse::Method(at).into());
+  }
+
+  let at = line.offset();
+  let target = line.take_while(|b| b.is_ascii_graphic());
+  let end = line.offset();
+  if target.is_empty() || line.next() != Some(b' ') {
+    return Err(Parse::Uri(end).into());
+  }
+  let target = String::from_utf8_lossy(target);
+  let uri = if method == RequestMethod::Connect {
+    // CONNECT names the host and port to tunnel to (authority-form), which becomes the path.
+    if !is_authority(&target) {
+      return Err(Parse::Uri(at).into());
+    }
+    Uri::from_parts(&target, "")
+  } else {
+    let target = target.split('#').next().unwrap_or_default();
+    // Absolute-form targets ("http://host/path") are routed on their path alone.
+    let target = match target.split_once("://") {
+      Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+      None => target,
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if !path.starts_with('/') && path != "*" {
+      return Err(Parse::Uri(at).into());
+    }
+    Uri::from_parts(path, query)
+  };
+
+  let at = line.offset();
+  let version = match line.rest() {
+    b"HTTP/1.1" => Version::Http11,
+    b"HTTP/1.0" => Version::Http10,
+    v if v.starts_with(b"HTTP/") => return Err(Error::new(Kind::UnsupportedVersion)),
+    _ => return Err(Parse::Version(at).into()),
+  };
+  Ok((method, uri, version))
+}
+
+/// Whether `target` is `host:port`, with the host a name, an IPv4 address or a bracketed IPv6
+/// address.
+fn is_authority(target: &str) -> bool {
+  let Some((host, port)) = target.rsplit_once(':') else {
+    return false;
+  };
+  let host_ok = match host.strip_prefix('[') {
+    Some(v6) => v6
+      .strip_suffix(']')
+      .is_some_and(|v6| v6.parse::<std::net::Ipv6Addr>().is_ok()),
+    None => {
+      !host.is_empty()
+        && host
+          .bytes()
+          .all(|b| b.is_ascii_alphanumeric() || b"-._~".contains(&b))
+    }
+  };
+  host_ok && !port.is_empty() && port.len() <= 5 && port.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Parses one `name: value` field line.
+pub fn header(mut line: Line) -> Result<(String, String), Error> {
+  let at = line.offset();
+  let name = line.take_while(is_tchar);
+  // An empty name also catches obsolete line folding, which starts with whitespace.
+  if name.is_empty() {
+    return Err(Parse::Header(at).into());
+  }
+  let name = String::from_utf8_lossy(name).into_owned();
+  let at = line.offset();
+  if line.next() != Some(b':') {
+    return Err(Parse::Header(at).into());
+  }
+  line.take_while(|b| b == b' ' || b == b'\t');
+  let at = line.offset();
+  let value = line.rest();
+  if let Some(i) = value
+    .iter()
+    .position(|b| b.is_ascii_control() && *b != b'\t')
+  {
+    return Err(Parse::Header(at + i).into());
+  }
+  let value = String::from_utf8_lossy(value).trim_end().to_string();
+  Ok((name, value))
+}
+
+/// A cursor over one line of input that never reads past the end of it.
+#[derive(Debug)]
+pub struct Line<'a> {
+  bytes: &'a [u8],
+  pos: usize,
+  offset: usize,
+}
+impl<'a> Line<'a> {
+  pub fn new(bytes: &'a [u8], offset: usize) -> Self {
+    Self {
+      bytes,
+      pos: 0,
+      offset,
+    }
+  }
+  pub fn is_empty(&self) -> bool {
+    self.bytes.is_empty()
+  }
+  /// Position of the cursor relative to the start of the request.
+  pub fn offset(&self) -> usize {
+    self.offset + self.pos
+  }
+  pub fn next(&mut self) -> Option<u8> {
+    let byte = *self.bytes.get(self.pos)?;
+    self.pos += 1;
+    Some(byte)
+  }
+  pub fn take_while(&mut self, test: fn(u8) -> bool) -> &'a [u8] {
+    let start = self.pos;
+    while self.bytes.get(self.pos).is_some_and(|b| test(*b)) {
+      self.pos += 1;
+    }
+    &self.bytes[start..self.pos]
+  }
+  pub fn rest(&mut self) -> &'a [u8] {
+    let rest = &self.bytes[self.pos..];
+    self.pos = self.bytes.len();
+    rest
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_a_request_fed_in_pieces() {
+    let mut parser = Parser::new(Limits::default());
+    let mut buf = b"GET /search?q=rust HT".to_vec();
+    assert!(matches!(parser.parse(&mut buf).unwrap(), Status::Partial));
+    assert_eq!(buf, b"GET /search?q=rust HT");
+    buf.extend_from_slice(b"TP/1.1\r\nHost: example.com\r\nAccept:  */* \r\n\r\nbody");
+    let Status::Complete(request) = parser.parse(&mut buf).unwrap() else {
+      panic!("request should be complete");
+    };
+    assert_eq!(request.uri().path(), "/search");
+    assert_eq!(request.uri().query(), "q=rust");
+    assert_eq!(request.headers().get("accept"), Some("*/*"));
+    assert_eq!(request.version(), Version::Http11);
+    assert_eq!(buf, b"body");
+  }
+
+  #[test]
+  fn truncated_input_needs_more_data() {
+    for input in [&b"GE"[..], b"GET / HTTP/1.1\r", b"GET / HTTP/1.1\r\nHost:"] {
+      let mut buf = input.to_vec();
+      let status = Parser::new(Limits::default()).parse(&mut buf).unwrap();
+      assert!(matches!(status, Status::Partial));
+    }
+  }
+
+  #[test]
+  fn errors_point_at_the_offending_byte() {
+    let mut buf = b"GET / HTTP/1.1\r\nBad Header: x\r\n\r\n".to_vec();
+    let err = Parser::new(Limits::default()).parse(&mut buf).unwrap_err();
+    assert!(matches!(err.kind(), Kind::Parse(Parse::Header(19))));
+
+    let mut buf = b"GET /  HTTP/1.1\r\n\r\n".to_vec();
+    let err = Parser::new(Limits::default()).parse(&mut buf).unwrap_err();
+    assert!(matches!(err.kind(), Kind::Parse(Parse::Version(6))));
+  }
+
+  #[test]
+  fn every_method_reaches_the_request() {
+    for line in [
+      "HEAD / HTTP/1.1",
+      "OPTIONS * HTTP/1.1",
+      "TRACE /a HTTP/1.1",
+      "CONNECT example.com:443 HTTP/1.1",
+      "CONNECT [::1]:8080 HTTP/1.1",
+      "PROPFIND /dav HTTP/1.1",
+    ] {
+      let mut buf = format!("{line}\r\n\r\n").into_bytes();
+      let Status::Complete(request) = Parser::new(Limits::default()).parse(&mut buf).unwrap()
+      else {
+        panic!("request should be complete");
+      };
+      let (method, target) = line.split_once(' ').unwrap();
+      assert_eq!(request.method().as_str(), method);
+      assert_eq!(request.uri().path(), target.split(' ').next().unwrap());
+    }
+    for line in [
+      "CONNECT /path HTTP/1.1",
+      "CONNECT example.com HTTP/1.1",
+      "CONNECT example.com:https HTTP/1.1",
+      "GET example.com:443 HTTP/1.1",
+      "G(T / HTTP/1.1",
+    ] {
+      let mut buf = format!("{line}\r\n\r\n").into_bytes();
+      assert!(
+        Parser::new(Limits::default()).parse(&mut buf).is_err(),
+        "{line}"
+      );
+    }
+  }
+
+  fn framing(head: &str) -> Result<Framing, Error> {
+    let mut buf = format!("POST / HTTP/1.1\r\n{head}\r\n").into_bytes();
+    let Status::Complete(request) = Parser::new(Limits::default()).parse(&mut buf)? else {
+      panic!("request should be complete");
+    };
+    Framing::of(&request)
+  }
+
+  #[test]
+  fn framing_rejects_ambiguous_lengths() {
+    assert_eq!(framing("").unwrap(), Framing::Length(0));
+    assert_eq!(
+      framing("Content-Length: 5, 5\r\n").unwrap(),
+      Framing::Length(5)
+    );
+    assert_eq!(
+      framing("Transfer-Encoding: , chunked\r\n").unwrap(),
+      Framing::Chunked
+    );
+    assert_eq!(
+      framing("Content-Length: 99999999999999999999999\r\n").unwrap(),
+      Framing::Length(usize::MAX)
+    );
+    for head in [
+      "Content-Length: \r\n",
+      "Content-Length: 5,\r\n",
+      "Content-Length: 5\r\nContent-Length: \r\n",
+      "Content-Length: 5, 6\r\n",
+      "Content-Length: +5\r\n",
+    ] {
+      let err = framing(head).unwrap_err();
+      assert!(
+        matches!(err.kind(), Kind::Parse(Parse::ContentLength)),
+        "{head}"
+      );
+    }
+  }
+
+  #[test]
+  fn limits_are_enforced() {
+    let limits = Limits {
+      max_request_line: 16,
+      max_headers: 1,
+      max_header_size: 16,
+    };
+    let mut buf = b"GET /a-very-long-path".to_vec();
+    let err = Parser::new(limits.clone()).parse(&mut buf).unwrap_err();
+    assert!(matches!(
+      err.kind(),
+      Kind::Parse(Parse::RequestLineTooLong(0))
+    ));
+
+    let mut buf = b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\n\r\n".to_vec();
+    let err = Parser::new(limits.clone()).parse(&mut buf).unwrap_err();
+    assert!(matches!(err.kind(), Kind::Parse(Parse::TooManyHeaders)));
+
+    let mut buf = b"GET / HTTP/1.1\r\nCookie: aaaaaaaaaaaaaaaa".to_vec();
+    let err = Parser::new(limits).parse(&mut buf).unwrap_err();
+    assert!(matches!(err.kind(), Kind::Parse(Parse::HeaderTooLarge(16))));
+
+    // Raised limits let targets past 64 KiB through with their query intact.
+    let limits = Limits {
+      max_request_line: 128 * 1024,
+      ..Limits::default()
+    };
+    let path = format!("/{}", "a".repeat(70_000));
+    let mut buf = format!("GET {path}?q=1 HTTP/1.1\r\n\r\n").into_bytes();
+    let Status::Complete(request) = Parser::new(limits).parse(&mut buf).unwrap() else {
+      panic!("request should be complete");
+    };
+    assert_eq!(request.uri().path(), path);
+    assert_eq!(request.uri().query(), "q=1");
+  }
+}
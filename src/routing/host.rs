@@ -0,0 +1,296 @@
+use async_trait::async_trait;
+
+use super::{RouteError, Router};
+use crate::handler::*;
+use crate::http::typed::Host;
+use crate::http::*;
+
+/// One `.`-separated piece of a host pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Label {
+  Static(String),
+  /// `:name` matches any single label and captures it.
+  Param(String),
+  /// `*` as the first label matches one or more labels. `*name` captures them, dots included.
+  Rest(Option<String>),
+}
+
+struct HostRoute {
+  pattern: String,
+  labels: Vec<Label>,
+  router: Router,
+}
+
+impl HostRoute {
+  fn parse(pattern: &str) -> Result<Vec<Label>, RouteError> {
+    let invalid = |reason: &str| RouteError::InvalidPattern(pattern.into(), reason.into());
+    let parts = pattern.split('.').collect::<Vec<_>>();
+    let mut labels = vec![];
+    for (i, part) in parts.iter().enumerate() {
+      let label = if let Some(name) = part.strip_prefix(':') {
+        Label::Param(name.into())
+      } else if let Some(name) = part.strip_prefix('*') {
+        if i != 0 {
+          return Err(invalid("`*` must be the first label"));
+        }
+        Label::Rest((!name.is_empty()).then(|| name.into()))
+      } else if part.is_empty() {
+        return Err(invalid("empty label"));
+      } else {
+        Label::Static(part.to_ascii_lowercase())
+      };
+      if let Label::Param(name) | Label::Rest(Some(name)) = &label {
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+          return Err(invalid("invalid parameter name"));
+        }
+      }
+      labels.push(label);
+    }
+    Ok(labels)
+  }
+
+  /// The parameters captured from `host`, if it matches.
+  fn matches(&self, host: &str) -> Option<Params> {
+    let host = host.split('.').collect::<Vec<_>>();
+    let mut params = Params::new();
+    let (labels, host) = match self.labels.split_first() {
+      Some((Label::Rest(name), rest)) => {
+        // At least one label has to be left for the `*`.
+        let split = host.len().checked_sub(rest.len()).filter(|n| *n > 0)?;
+        if let Some(name) = name {
+          params.insert(name, host[..split].join("."));
+        }
+        (rest, &host[split..])
+      }
+      _ => (&self.labels[..], &host[..]),
+    };
+    if labels.len() != host.len() {
+      return None;
+    }
+    for (label, part) in labels.iter().zip(host) {
+      match label {
+        Label::Static(s) if s == part => {}
+        Label::Param(name) if !part.is_empty() => params.insert(name, *part),
+        _ => return None,
+      }
+    }
+    Some(params)
+  }
+
+  /// Lower is more specific: exact names, then patterns with `:name` labels, then `*`.
+  fn rank(&self) -> u8 {
+    if self.labels.iter().any(|l| matches!(l, Label::Rest(_))) {
+      2
+    } else if self.labels.iter().any(|l| matches!(l, Label::Param(_))) {
+      1
+    } else {
+      0
+    }
+  }
+}
+
+/// Picks a [`Router`] by the request's `Host` header, e.g. for serving `example.com` and
+/// `*.example.com` from one server. Exact host names win over `:name` labels, which win over
+/// `*`, and patterns of the same kind are tried in the order they were added. Labels captured by
+/// the pattern become path parameters.
+pub struct HostRouter {
+  hosts: Vec<HostRoute>,
+  default: Option<Router>,
+  unknown_host: StatusCode,
+}
+
+impl HostRouter {
+  pub fn builder() -> HostRouterBuilder {
+    HostRouterBuilder::new()
+  }
+  /// The router for `host`, which may carry a port, with the parameters captured from it.
+  pub fn route(&self, host: &str) -> Option<(&Router, Params)> {
+    let host = Host::decode(host).ok()?.host;
+    let (i, params) = self
+      .hosts
+      .iter()
+      .enumerate()
+      .find_map(|(i, route)| Some((i, route.matches(&host)?)))?;
+    Some((&self.hosts[i].router, params))
+  }
+}
+
+#[async_trait]
+impl Handler<Request> for HostRouter
+where
+  Request: Send,
+{
+  type Response = Response;
+  async fn call(&self, mut request: Request) -> Self::Response {
+    let host = request
+      .headers()
+      .get("Host")
+      .unwrap_or_default()
+      .to_string();
+    let router = match self.route(&host) {
+      Some((router, params)) => {
+        *request.params_mut() = params;
+        router
+      }
+      None => match &self.default {
+        Some(router) => router,
+        None => return self.unknown_host.clone().into_response(),
+      },
+    };
+    router.dispatch(request).await
+  }
+}
+
+pub struct HostRouterBuilder {
+  hosts: Vec<(String, Router)>,
+  default: Option<Router>,
+  unknown_host: StatusCode,
+}
+
+impl HostRouterBuilder {
+  pub fn new() -> Self {
+    Self {
+      hosts: vec![],
+      default: None,
+      unknown_host: StatusCode::MisdirectedRequest,
+    }
+  }
+  /// Serves `pattern` with `router`. Patterns are host names, optionally with `:name` labels
+  /// matching any one label, or a leading `*` or `*name` matching one or more, e.g.
+  /// `:tenant.example.com` or `*.example.com`.
+  pub fn host(mut self, pattern: &str, router: Router) -> Self {
+    self.hosts.push((pattern.to_string(), router));
+    self
+  }
+  /// Serves requests for any other host, or without a `Host` header.
+  pub fn fallback(mut self, router: Router) -> Self {
+    self.default = Some(router);
+    self
+  }
+  /// The status for hosts nothing serves when there is no default router, 421 Misdirected
+  /// Request unless set.
+  pub fn unknown_host(mut self, status: StatusCode) -> Self {
+    self.unknown_host = status;
+    self
+  }
+  /// Fails with every invalid pattern and every pattern given twice.
+  pub fn build(self) -> Result<HostRouter, Vec<RouteError>> {
+    let mut errors = vec![];
+    let mut hosts = vec![];
+    for (pattern, router) in self.hosts {
+      let labels = match HostRoute::parse(&pattern) {
+        Ok(labels) => labels,
+        Err(err) => {
+          errors.push(err);
+          continue;
+        }
+      };
+      if let Some(earlier) = hosts.iter().find(|h: &&HostRoute| h.labels == labels) {
+        errors.push(RouteError::Conflict(earlier.pattern.clone(), pattern));
+        continue;
+      }
+      hosts.push(HostRoute {
+        pattern,
+        labels,
+        router,
+      });
+    }
+    if !errors.is_empty() {
+      return Err(errors);
+    }
+    hosts.sort_by_key(HostRoute::rank);
+    Ok(HostRouter {
+      hosts,
+      default: self.default,
+      unknown_host: self.unknown_host,
+    })
+  }
+}
+
+impl Default for HostRouterBuilder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  async fn not_found(_: Request) -> Response {
+    StatusCode::NotFound.into_response()
+  }
+
+  fn router(name: &'static str) -> Router {
+    Router::builder(not_found)
+      .get("/", move |r: Request| async move {
+        let mut params = r.params().iter().collect::<Vec<_>>();
+        params.sort();
+        format!("{name} {params:?}")
+      })
+      .build()
+      .unwrap()
+  }
+
+  fn request(host: &str) -> Request {
+    let mut headers = HeaderMap::new();
+    headers.append("Host", host).unwrap();
+    Request::new(
+      RequestMethod::Get,
+      Uri::from_str("/"),
+      Version::Http11,
+      headers,
+      Body::default(),
+    )
+  }
+
+  #[tokio::test]
+  async fn hosts_pick_the_router() {
+    let hosts = HostRouter::builder()
+      .host("*.example.com", router("any"))
+      .host(":tenant.example.com", router("tenant"))
+      .host("www.example.com", router("www"))
+      .host("*sub.example.org", router("org"))
+      .build()
+      .unwrap();
+    for (host, body) in [
+      ("WWW.example.com:8080", "www []"),
+      ("acme.example.com", r#"tenant [("tenant", "acme")]"#),
+      ("a.b.example.com", "any []"),
+      ("a.b.example.org", r#"org [("sub", "a.b")]"#),
+    ] {
+      let response = hosts.call(request(host)).await;
+      assert_eq!(response.body().get_bytes(), body.as_bytes(), "{host}");
+    }
+    let response = hosts.call(request("example.com")).await;
+    assert_eq!(*response.status_code(), StatusCode::MisdirectedRequest);
+
+    let hosts = HostRouter::builder()
+      .host("example.com", router("main"))
+      .unknown_host(StatusCode::NotFound)
+      .build()
+      .unwrap();
+    let response = hosts.call(request("other.com")).await;
+    assert_eq!(*response.status_code(), StatusCode::NotFound);
+
+    let hosts = HostRouter::builder()
+      .host("example.com", router("main"))
+      .fallback(router("fallback"))
+      .build()
+      .unwrap();
+    let response = hosts.call(request("other.com")).await;
+    assert_eq!(response.body().get_bytes(), b"fallback []");
+  }
+
+  #[test]
+  fn invalid_and_duplicate_hosts_are_reported() {
+    let errors = HostRouter::builder()
+      .host("a.*.com", router("a"))
+      .host(":x.example.com", router("b"))
+      .host(":x.example.com", router("c"))
+      .build()
+      .err()
+      .unwrap();
+    assert_eq!(errors.len(), 2);
+  }
+}
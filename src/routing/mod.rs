@@ -1,7 +1,10 @@
+mod host;
+mod node;
 mod route;
 mod route_handler;
 mod router;
 
+pub use host::*;
 pub use route::*;
 pub use route_handler::*;
 pub use router::*;
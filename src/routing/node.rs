@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use super::{Constraint, Segment};
+
+/// A segment tree over route patterns. Each level matches one path segment, trying static
+/// children first, then parameters, then wildcards, then catch-alls, so the most specific route
+/// wins no matter the order routes were registered in. Routes are referred to by their index in
+/// the router.
+#[derive(Debug, Default)]
+pub(crate) struct Node {
+  statics: HashMap<String, Node>,
+  /// Parameter children, those with constraints first.
+  params: Vec<(Option<Constraint>, Node)>,
+  wildcard: Option<Box<Node>>,
+  /// Routes whose pattern ends at this node.
+  routes: Vec<usize>,
+  /// Routes whose pattern ends with a catch-all below this node.
+  catch_all: Vec<usize>,
+}
+
+impl Node {
+  pub fn insert(&mut self, segments: &[Segment], route: usize) {
+    let Some((first, rest)) = segments.split_first() else {
+      self.routes.push(route);
+      return;
+    };
+    let child = match first {
+      Segment::Static(s) => self.statics.entry(s.clone()).or_default(),
+      Segment::Param(_, constraint) => {
+        let i = match self.params.iter().position(|(c, _)| c == constraint) {
+          Some(i) => i,
+          None => {
+            // Constrained parameters are more specific, so they are tried first.
+            let i = match constraint {
+              Some(_) => self.params.iter().take_while(|(c, _)| c.is_some()).count(),
+              None => self.params.len(),
+            };
+            self.params.insert(i, (constraint.clone(), Node::default()));
+            i
+          }
+        };
+        &mut self.params[i].1
+      }
+      Segment::Wildcard => self.wildcard.get_or_insert_with(Default::default),
+      Segment::CatchAll(_) => {
+        self.catch_all.push(route);
+        return;
+      }
+    };
+    child.insert(rest, route);
+  }
+
+  /// Walks the routes matching `path`, most specific first, passing each one the values its
+  /// parameters, wildcards and catch-all captured, in pattern order. Stops as soon as `visit`
+  /// returns true, and returns whether it did.
+  ///
+  /// When one branch matches at each level this takes time linear in the path length. Dead ends
+  /// send the walk back to less specific branches, but each node sits at one depth and is only
+  /// reachable one way, so no node is entered twice: the worst case is bounded by the size of
+  /// the tree, not exponential in the path length.
+  pub fn visit(
+    &self,
+    path: &[&str],
+    captures: &mut Vec<String>,
+    visit: &mut dyn FnMut(usize, &[String]) -> bool,
+  ) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+      return self.routes.iter().any(|&route| visit(route, captures));
+    };
+    if let Some(child) = self.statics.get(*first) {
+      if child.visit(rest, captures, visit) {
+        return true;
+      }
+    }
+    if !first.is_empty() {
+      let params = self
+        .params
+        .iter()
+        .filter(|(c, _)| c.as_ref().is_none_or(|c| c.matches(first)))
+        .map(|(_, child)| child);
+      for child in params.chain(self.wildcard.as_deref()) {
+        captures.push(first.to_string());
+        if child.visit(rest, captures, visit) {
+          return true;
+        }
+        captures.pop();
+      }
+    }
+    // The remainder is only built where a catch-all can take it. It is empty exactly when the
+    // path ends in a single empty segment.
+    if !self.catch_all.is_empty() && path != [""] {
+      captures.push(path.join("/"));
+      if self.catch_all.iter().any(|&route| visit(route, captures)) {
+        return true;
+      }
+      captures.pop();
+    }
+    false
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tree(patterns: &[&str]) -> Node {
+    let mut node = Node::default();
+    for (i, pattern) in patterns.iter().enumerate() {
+      node.insert(&Segment::parse(pattern).unwrap(), i);
+    }
+    node
+  }
+
+  fn first(node: &Node, path: &str) -> Option<(usize, Vec<String>)> {
+    let segments = path[1..].split('/').collect::<Vec<_>>();
+    let mut found = None;
+    node.visit(&segments, &mut vec![], &mut |route, captures| {
+      found = Some((route, captures.to_vec()));
+      true
+    });
+    found
+  }
+
+  #[test]
+  fn static_beats_param_beats_wildcard_beats_catch_all() {
+    let node = tree(&["/files/*", "/users/*/x", "/users/:id/x", "/users/me/x", "/"]);
+    assert_eq!(first(&node, "/users/me/x"), Some((3, vec![])));
+    assert_eq!(first(&node, "/users/42/x"), Some((2, vec!["42".into()])));
+    assert_eq!(
+      first(&node, "/files/a/b.txt"),
+      Some((0, vec!["a/b.txt".into()]))
+    );
+    assert_eq!(first(&node, "/"), Some((4, vec![])));
+    assert_eq!(first(&node, "/files/"), None);
+    assert_eq!(first(&node, "/users//x"), None);
+  }
+
+  #[test]
+  fn dead_ends_fall_back_to_less_specific_branches() {
+    let node = tree(&["/a/:b/c", "/a/b/d"]);
+    assert_eq!(first(&node, "/a/b/c"), Some((0, vec!["b".into()])));
+    assert_eq!(first(&node, "/a/b/d"), Some((1, vec![])));
+  }
+}
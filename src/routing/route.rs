@@ -1,53 +1,387 @@
 use regex::Regex;
 
+use super::Router;
 use crate::handler::*;
 use crate::http::*;
-pub type Params = std::collections::HashMap<String, String>;
+
+/// The methods a route answers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MethodSet {
+  Any,
+  Only(Vec<RequestMethod>),
+}
+impl MethodSet {
+  pub fn contains(&self, method: &RequestMethod) -> bool {
+    match self {
+      Self::Any => true,
+      Self::Only(methods) => methods.contains(method),
+    }
+  }
+}
+impl From<RequestMethod> for MethodSet {
+  fn from(method: RequestMethod) -> Self {
+    Self::Only(vec![method])
+  }
+}
+impl From<Vec<RequestMethod>> for MethodSet {
+  fn from(methods: Vec<RequestMethod>) -> Self {
+    Self::Only(methods)
+  }
+}
+impl<const N: usize> From<[RequestMethod; N]> for MethodSet {
+  fn from(methods: [RequestMethod; N]) -> Self {
+    Self::Only(methods.into())
+  }
+}
+
+/// One `/`-separated piece of a route pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+  /// Matches the segment exactly.
+  Static(String),
+  /// `:name` matches any non-empty segment and captures it. `:name(constraint)` only matches
+  /// segments that satisfy the constraint.
+  Param(String, Option<Constraint>),
+  /// `*` in the middle of a pattern matches any non-empty segment.
+  Wildcard,
+  /// `*` at the end of a pattern matches the rest of the path, slashes included. `*name`
+  /// captures it as a parameter.
+  CatchAll(Option<String>),
+}
+impl Segment {
+  /// Splits a pattern such as `/users/:id(int)/*rest` into segments, or lists everything that
+  /// is wrong with it.
+  pub fn parse(pattern: &str) -> Result<Vec<Segment>, Vec<RouteError>> {
+    let invalid = |reason: String| RouteError::InvalidPattern(pattern.into(), reason);
+    let Some(path) = pattern.strip_prefix('/') else {
+      return Err(vec![invalid("must start with `/`".into())]);
+    };
+    let parts = path.split('/').collect::<Vec<_>>();
+    let mut segments = vec![];
+    let mut errors = vec![];
+    let mut names = vec![];
+    let mut check_name = |name: &str, errors: &mut Vec<RouteError>| {
+      if name.is_empty() {
+        errors.push(invalid("parameter without a name".into()));
+      } else if !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+        errors.push(invalid(format!("invalid parameter name `{name}`")));
+      } else if names.contains(&name.to_string()) {
+        errors.push(RouteError::DuplicateParam(pattern.into(), name.into()));
+      } else {
+        names.push(name.to_string());
+      }
+    };
+    for (i, part) in parts.iter().enumerate() {
+      let last = i == parts.len() - 1;
+      if let Some(param) = part.strip_prefix(':') {
+        let (name, constraint) = match param.split_once('(') {
+          Some((name, rest)) => match rest.strip_suffix(')').map(Constraint::parse) {
+            Some(Ok(constraint)) => (name, Some(constraint)),
+            Some(Err(reason)) => {
+              errors.push(invalid(reason));
+              continue;
+            }
+            None => {
+              errors.push(invalid(format!("unclosed constraint in `{part}`")));
+              continue;
+            }
+          },
+          None => (param, None),
+        };
+        check_name(name, &mut errors);
+        segments.push(Segment::Param(name.into(), constraint));
+      } else if let Some(name) = part.strip_prefix('*') {
+        match (name, last) {
+          ("", false) => segments.push(Segment::Wildcard),
+          ("", true) => segments.push(Segment::CatchAll(None)),
+          (_, false) => errors.push(invalid("a named `*` must be the last segment".into())),
+          (name, true) => {
+            check_name(name, &mut errors);
+            segments.push(Segment::CatchAll(Some(name.into())));
+          }
+        }
+      } else if part.contains('*') {
+        errors.push(invalid("`*` must start a segment".into()));
+      } else {
+        segments.push(Segment::Static(part.to_string()));
+      }
+    }
+    if errors.is_empty() {
+      Ok(segments)
+    } else {
+      Err(errors)
+    }
+  }
+}
+
+/// What a constrained parameter accepts: `int` (an optionally signed integer), `uuid`, `slug`
+/// (letters, digits, `-` and `_`), or any other regex, which must match the whole segment.
+#[derive(Debug, Clone)]
+pub enum Constraint {
+  Int,
+  Uuid,
+  Slug,
+  Regex(Regex),
+}
+impl Constraint {
+  fn parse(constraint: &str) -> Result<Self, String> {
+    match constraint {
+      "int" => Ok(Self::Int),
+      "uuid" => Ok(Self::Uuid),
+      "slug" => Ok(Self::Slug),
+      regex => Regex::new(&format!("^(?:{regex})$"))
+        .map(Self::Regex)
+        .map_err(|_| format!("invalid constraint `{regex}`")),
+    }
+  }
+  pub fn matches(&self, value: &str) -> bool {
+    match self {
+      Self::Int => {
+        let digits = value.strip_prefix('-').unwrap_or(value);
+        !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+      }
+      Self::Uuid => value.parse::<Uuid>().is_ok(),
+      Self::Slug => {
+        !value.is_empty()
+          && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+      }
+      Self::Regex(regex) => regex.is_match(value),
+    }
+  }
+}
+impl PartialEq for Constraint {
+  fn eq(&self, other: &Self) -> bool {
+    match (self, other) {
+      (Self::Regex(a), Self::Regex(b)) => a.as_str() == b.as_str(),
+      (a, b) => std::mem::discriminant(a) == std::mem::discriminant(b),
+    }
+  }
+}
+impl Eq for Constraint {}
+
+/// A problem with the routes given to [`RouterBuilder`](super::RouterBuilder).
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum RouteError {
+  #[error("invalid route pattern `{0}`: {1}")]
+  InvalidPattern(String, String),
+  #[error("route `{0}` uses the parameter `{1}` more than once")]
+  DuplicateParam(String, String),
+  #[error("route `{1}` can never match: `{0}` and earlier routes take all its paths and methods")]
+  Conflict(String, String),
+  #[error("more than one route is named `{0}`")]
+  DuplicateName(String),
+  #[error("the name `{0}` was given before any route was added")]
+  NameWithoutRoute(String),
+}
+
+/// What a route dispatches to.
+pub(crate) enum Target {
+  Handler(Box<dyn Handler<Request, Response = Response> + Send>),
+  /// A router mounted under the route's pattern, which matches the rest of the path.
+  Router(Router),
+}
 
 pub struct Route {
-  method: RequestMethod,
-  regex: Regex,
-  handler: Box<dyn Handler<Request, Response = Response> + Send>,
+  name: Option<String>,
+  methods: MethodSet,
+  pattern: String,
+  segments: Vec<Segment>,
+  /// Problems found while parsing the pattern, reported when the router is built.
+  errors: Vec<RouteError>,
+  target: Target,
 }
 impl Route {
-  pub fn new<H: Handler<Request, Response = Response> + Send + 'static>(
-    method: RequestMethod,
-    path: &str,
-    handler: H,
-  ) -> Self {
-    let new_scheme = path
-      .split('/')
-      .map(|s| {
-        if let Some(s) = s.strip_prefix(":") {
-          format!("(?P<{s}>[^/]+)")
-        } else {
-          s.to_string()
-        }
-      })
-      .map(|s| s.replace("*", "[\\w.\\-_]+"))
-      .collect::<Vec<_>>()
-      .join("/");
+  /// Handlers may return anything that converts into a response, so a handler returning
+  /// `Result<Response, Error>` can use `?` on [`Request::param`].
+  pub fn new<H>(methods: impl Into<MethodSet>, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    let (segments, errors) = match Segment::parse(path) {
+      Ok(segments) => (segments, vec![]),
+      Err(errors) => (vec![], errors),
+    };
+    Self {
+      name: None,
+      methods: methods.into(),
+      pattern: path.to_string(),
+      segments,
+      errors,
+      target: Target::Handler(Box::new(Responder(handler))),
+    }
+  }
+  /// Mounts `router` under `prefix` for every method. The prefix matches on its own, with a
+  /// trailing slash, or followed by any path, which the nested router then matches against.
+  pub fn nested(prefix: &str, router: Router) -> Self {
+    let (mut segments, mut errors) = match prefix.trim_end_matches('/') {
+      "" => (vec![], vec![]),
+      prefix => match Segment::parse(prefix) {
+        Ok(segments) => (segments, vec![]),
+        Err(errors) => (vec![], errors),
+      },
+    };
+    if let Some(Segment::CatchAll(_)) = segments.last() {
+      errors.push(RouteError::InvalidPattern(
+        prefix.into(),
+        "a prefix can't end with `*`".into(),
+      ));
+    }
+    segments.push(Segment::CatchAll(None));
     Self {
-      method,
-      regex: Regex::new(&format!("^{}$", new_scheme)).unwrap(),
-      handler: Box::new(handler),
+      name: None,
+      methods: MethodSet::Any,
+      pattern: prefix.to_string(),
+      segments,
+      errors,
+      target: Target::Router(router),
     }
   }
-  pub fn matches(&self, path: &str) -> Option<Params> {
-    let caps = self.regex.captures(path)?;
-    let map = self
-      .regex
-      .capture_names()
+  /// Problems with the pattern itself, as opposed to how it relates to other routes.
+  pub fn validate(&self) -> Vec<RouteError> {
+    self.errors.clone()
+  }
+  /// Whether some path and method are matched by both routes, so that the one registered first
+  /// takes them.
+  pub fn conflicts_with(&self, other: &Route) -> bool {
+    let shared_method = match (&self.methods, &other.methods) {
+      (MethodSet::Any, _) | (_, MethodSet::Any) => true,
+      (MethodSet::Only(a), MethodSet::Only(b)) => a.iter().any(|m| b.contains(m)),
+    };
+    let shared_shape = self
+      .shapes()
+      .iter()
+      .any(|a| other.shapes().iter().any(|b| same_shape(a, b)));
+    shared_shape && shared_method
+  }
+  /// The earlier route `self` conflicts with, if the routes in `earlier` leave it nothing to
+  /// match: every path it matches, with every method it takes, is taken by one of them first.
+  pub(crate) fn shadowed_by<'a>(&self, earlier: &[&'a Route]) -> Option<&'a Route> {
+    let covered = self.shapes().iter().all(|shape| {
+      let methods = earlier
+        .iter()
+        .filter(|r| r.shapes().iter().any(|s| same_shape(s, shape)))
+        .map(|r| &r.methods)
+        .collect::<Vec<_>>();
+      match &self.methods {
+        MethodSet::Any => methods.contains(&&MethodSet::Any),
+        MethodSet::Only(own) => own.iter().all(|m| methods.iter().any(|s| s.contains(m))),
+      }
+    });
+    covered
+      .then(|| earlier.iter().find(|r| r.conflicts_with(self)).copied())
       .flatten()
-      .filter_map(|name| Some((name.to_string(), caps.name(name)?.as_str().to_string())))
-      .collect::<Params>();
-    Some(map)
   }
-  pub fn method(&self) -> &RequestMethod {
-    &self.method
+  /// Whether both routes' patterns match exactly the same paths, whatever their names.
+  pub(crate) fn same_shape(&self, other: &Route) -> bool {
+    same_shape(&self.segments, &other.segments)
+  }
+  /// The patterns the route is indexed under. A nested router's prefix also matches on its own
+  /// and with a trailing slash.
+  pub(crate) fn shapes(&self) -> Vec<Vec<Segment>> {
+    if !self.is_nested() {
+      return vec![self.segments.clone()];
+    }
+    let prefix = &self.segments[..self.segments.len() - 1];
+    vec![
+      prefix.to_vec(),
+      [prefix, &[Segment::Static(String::new())]].concat(),
+      self.segments.clone(),
+    ]
+  }
+  /// Names the route so [`Router::url_for`] can build links to it.
+  pub fn named(mut self, name: &str) -> Self {
+    self.name = Some(name.into());
+    self
+  }
+  pub fn name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+  pub(crate) fn target(&self) -> &Target {
+    &self.target
+  }
+  pub fn pattern(&self) -> &str {
+    &self.pattern
   }
+  pub fn segments(&self) -> &[Segment] {
+    &self.segments
+  }
+  /// Names the values captured while matching this route's pattern, percent-decoded so they
+  /// round-trip through [`Router::url_for`]. Wildcards and unnamed catch-alls capture a value
+  /// too, but have no name to store it under.
+  pub fn params(&self, captures: &[String]) -> Params {
+    self
+      .segments
+      .iter()
+      .filter(|s| !matches!(s, Segment::Static(_)))
+      .zip(captures)
+      .filter_map(|(segment, value)| match segment {
+        Segment::Param(name, _) | Segment::CatchAll(Some(name)) => {
+          Some((name.clone(), percent_decode(value)))
+        }
+        _ => None,
+      })
+      .collect()
+  }
+  pub fn methods(&self) -> &MethodSet {
+    &self.methods
+  }
+
+  pub fn is_nested(&self) -> bool {
+    matches!(self.target, Target::Router(_))
+  }
+}
+
+/// Undoes percent-encoding (RFC 3986, section 2.1). Malformed escapes are kept as they are.
+fn percent_decode(value: &str) -> String {
+  let bytes = value.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let escaped = bytes
+      .get(i + 1..i + 3)
+      .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+      .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+    match escaped {
+      Some(b) => {
+        out.push(b);
+        i += 3;
+      }
+      None => {
+        out.push(bytes[i]);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Whether two patterns match exactly the same paths.
+fn same_shape(a: &[Segment], b: &[Segment]) -> bool {
+  a.len() == b.len()
+    && a.iter().zip(b).all(|pair| match pair {
+      (Segment::Param(_, a), Segment::Param(_, b)) => a == b,
+      // An unconstrained parameter matches the same segments as `*`.
+      (Segment::Param(_, None), Segment::Wildcard)
+      | (Segment::Wildcard, Segment::Param(_, None)) => true,
+      (Segment::CatchAll(_), Segment::CatchAll(_)) => true,
+      (a, b) => a == b,
+    })
+}
+
+/// Adapts a handler with any [`IntoResponse`] output to one that returns a [`Response`].
+struct Responder<H>(H);
 
-  pub fn handler(&mut self) -> &mut (dyn Handler<Request, Response = Response> + Send) {
-    &mut *self.handler
+#[async_trait::async_trait]
+impl<H> Handler<Request> for Responder<H>
+where
+  H: Handler<Request> + Send,
+  H::Response: IntoResponse,
+{
+  type Response = Response;
+  async fn call(&self, request: Request) -> Response {
+    self.0.call(request).await.into_response()
   }
 }
@@ -18,13 +18,294 @@ where
   Request: Send,
 {
   type Response = Response;
-  async fn call(&mut self, request: Request) -> Self::Response {
-    match self
-      .router
-      .get_handler(request.method(), request.uri().path())
-    {
-      Some(mut handler) => handler.call(request).await,
-      None => self.router.not_found().call(request).await,
+  async fn call(&self, request: Request) -> Self::Response {
+    self.router.dispatch(request).await
+  }
+}
+
+impl Router {
+  /// Routes a request and calls the handler it lands on, answering 405 and OPTIONS itself.
+  /// Parameters already on the request, such as ones captured from the host, are kept.
+  pub async fn dispatch(&self, mut request: Request) -> Response {
+    match self.lookup(request.method(), request.uri().path()) {
+      Lookup::Found(handler, params, prefix) => {
+        for (name, value) in params.iter() {
+          request.params_mut().insert(name, value);
+        }
+        *request.prefix_mut() = prefix;
+        handler.call(request).await
+      }
+      Lookup::MethodNotAllowed(allowed) => {
+        let allow = allowed
+          .iter()
+          .map(RequestMethod::as_str)
+          .collect::<Vec<_>>()
+          .join(", ");
+        // OPTIONS is answered for every path that has routes, unless a route handles it.
+        let status = match request.method() {
+          RequestMethod::Options => StatusCode::NoContent,
+          _ => StatusCode::MethodNotAllowed,
+        };
+        Response::builder()
+          .status(status)
+          .header("Allow", &allow)
+          .body(())
+          .unwrap()
+      }
+      Lookup::NotFound(not_found) => not_found.call(request).await,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::prelude::Result;
+
+  async fn not_found(_: Request) -> Response {
+    StatusCode::NotFound.into_response()
+  }
+  async fn user(request: Request) -> Result<String> {
+    let id = request.param::<u64>("id")?;
+    Ok(format!("user {id}"))
+  }
+  fn request(method: RequestMethod, path: &str) -> Request {
+    let uri = Uri::from_str(path);
+    Request::new(
+      method,
+      uri,
+      Version::Http11,
+      HeaderMap::new(),
+      Body::default(),
+    )
+  }
+
+  #[tokio::test]
+  async fn params_reach_the_handler() {
+    let router = Router::builder(not_found)
+      .get("/users/:id", user)
+      .build()
+      .unwrap();
+    let handler = RouterHandler::new(router);
+    let response = handler.call(request(RequestMethod::Get, "/users/42")).await;
+    assert_eq!(response.body().get_bytes(), b"user 42");
+    let response = handler
+      .call(request(RequestMethod::Get, "/users/abc"))
+      .await;
+    assert_eq!(*response.status_code(), StatusCode::BadRequest);
+  }
+
+  #[tokio::test]
+  async fn generated_urls_round_trip() {
+    let router = Router::builder(not_found)
+      .get("/f/:name", |r: Request| async move {
+        r.params().get("name").unwrap().to_string()
+      })
+      .name("f")
+      .get("/g/*rest", |r: Request| async move {
+        r.params().get("rest").unwrap().to_string()
+      })
+      .name("g")
+      .build()
+      .unwrap();
+    let f = router.url_for("f", &[("name", "a b/c%")]).unwrap();
+    let g = router.url_for("g", &[("rest", "x y/z")]).unwrap();
+    let handler = RouterHandler::new(router);
+    let response = handler.call(request(RequestMethod::Get, &f)).await;
+    assert_eq!(response.body().get_bytes(), b"a b/c%");
+    let response = handler.call(request(RequestMethod::Get, &g)).await;
+    assert_eq!(response.body().get_bytes(), b"x y/z");
+  }
+
+  #[tokio::test]
+  async fn routes_are_selected_by_method() {
+    let router = Router::builder(not_found)
+      .get("/items", |_| async { "list" })
+      .post("/items", |_| async { "create" })
+      .route(
+        "/items/:id",
+        [RequestMethod::Put, RequestMethod::Patch],
+        |_| async { "update" },
+      )
+      .any("/echo", |r: Request| async move { r.method().to_string() })
+      .build()
+      .unwrap();
+    let handler = RouterHandler::new(router);
+    for (method, path, body) in [
+      (RequestMethod::Get, "/items", "list"),
+      (RequestMethod::Post, "/items", "create"),
+      (RequestMethod::Patch, "/items/1", "update"),
+      (RequestMethod::Extension("PURGE".into()), "/echo", "PURGE"),
+    ] {
+      let response = handler.call(request(method, path)).await;
+      assert_eq!(response.body().get_bytes(), body.as_bytes());
     }
   }
+
+  #[tokio::test]
+  async fn wrong_methods_get_405_and_options_is_automatic() {
+    let router = Router::builder(not_found)
+      .get("/items", |_| async { "list" })
+      .post("/items", |_| async { "create" })
+      .build()
+      .unwrap();
+    let handler = RouterHandler::new(router);
+    let response = handler.call(request(RequestMethod::Delete, "/items")).await;
+    assert_eq!(*response.status_code(), StatusCode::MethodNotAllowed);
+    assert_eq!(
+      response.headers().get("Allow"),
+      Some("GET, POST, HEAD, OPTIONS")
+    );
+
+    let response = handler
+      .call(request(RequestMethod::Options, "/items"))
+      .await;
+    assert_eq!(*response.status_code(), StatusCode::NoContent);
+    assert_eq!(
+      response.headers().get("Allow"),
+      Some("GET, POST, HEAD, OPTIONS")
+    );
+    let bytes = String::from_utf8(response.as_bytes()).unwrap();
+    assert!(!bytes.contains("Content-Length"));
+    assert!(bytes.ends_with("\r\n\r\n"));
+
+    let response = handler.call(request(RequestMethod::Head, "/items")).await;
+    assert_eq!(response.body().get_bytes(), b"list");
+
+    // A less specific route that takes HEAD doesn't beat the GET route for the path, but a HEAD
+    // route with the same pattern does.
+    let router = Router::builder(not_found)
+      .get("/u/me", |_| async { "me" })
+      .any("/u/:id", |_| async { "someone" })
+      .get("/v/me", |_| async { "me" })
+      .head("/v/me", |_| async { "head" })
+      .any("/v/:id", |_| async { "someone" })
+      .get("/w", |_| async { "get" })
+      .any("/w", |_| async { "any" })
+      .build()
+      .unwrap();
+    let handler = RouterHandler::new(router);
+    for (method, path, body) in [
+      (RequestMethod::Get, "/u/me", "me"),
+      (RequestMethod::Head, "/u/me", "me"),
+      (RequestMethod::Head, "/u/you", "someone"),
+      (RequestMethod::Head, "/v/me", "head"),
+      (RequestMethod::Head, "/w", "get"),
+      (RequestMethod::Post, "/w", "any"),
+    ] {
+      let response = handler.call(request(method, path)).await;
+      assert_eq!(response.body().get_bytes(), body.as_bytes(), "{path}");
+    }
+
+    let response = handler.call(request(RequestMethod::Delete, "/nope")).await;
+    assert_eq!(*response.status_code(), StatusCode::NotFound);
+  }
+
+  #[tokio::test]
+  async fn nested_routers_see_the_rest_of_the_path() {
+    async fn api_not_found(_: Request) -> Response {
+      StatusCode::Gone.into_response()
+    }
+    let posts = Router::builder(api_not_found)
+      .get("/", |r: Request| async move { r.nested_path().to_string() })
+      .get("/posts/:post", |r: Request| async move {
+        let params = r.params();
+        format!(
+          "{} {} {}",
+          r.prefix(),
+          params.get("user").unwrap(),
+          params.get("post").unwrap()
+        )
+      })
+      .build()
+      .unwrap();
+    let router = Router::builder(not_found)
+      .nest("/users/:user", posts)
+      .build()
+      .unwrap();
+    let handler = RouterHandler::new(router);
+    let response = handler
+      .call(request(RequestMethod::Get, "/users/7/posts/9"))
+      .await;
+    assert_eq!(response.body().get_bytes(), b"/users/7 7 9");
+    let response = handler.call(request(RequestMethod::Get, "/users/7")).await;
+    assert_eq!(response.body().get_bytes(), b"/");
+    let response = handler.call(request(RequestMethod::Get, "/users/7/")).await;
+    assert_eq!(response.body().get_bytes(), b"/");
+    let response = handler
+      .call(request(RequestMethod::Get, "/users/7/x"))
+      .await;
+    assert_eq!(*response.status_code(), StatusCode::Gone);
+    let response = handler.call(request(RequestMethod::Get, "/posts")).await;
+    assert_eq!(*response.status_code(), StatusCode::NotFound);
+  }
+
+  #[tokio::test]
+  async fn requests_run_concurrently() {
+    // Both requests wait for each other, so they only finish if neither blocks the other.
+    let barrier = std::sync::Arc::new(tokio::sync::Barrier::new(2));
+    let router = Router::builder(not_found)
+      .get("/wait", move |_| {
+        let barrier = barrier.clone();
+        async move {
+          barrier.wait().await;
+          "done"
+        }
+      })
+      .build()
+      .unwrap();
+    let handler = RouterHandler::new(router);
+    let both = async {
+      tokio::join!(
+        handler.call(request(RequestMethod::Get, "/wait")),
+        handler.call(request(RequestMethod::Get, "/wait")),
+      )
+    };
+    let (a, b) = tokio::time::timeout(std::time::Duration::from_secs(1), both)
+      .await
+      .unwrap();
+    assert_eq!(a.body().get_bytes(), b"done");
+    assert_eq!(b.body().get_bytes(), b"done");
+  }
+
+  #[tokio::test]
+  async fn constraints_and_named_catch_alls() {
+    let show = |r: Request| async move {
+      let mut params = r.params().iter().collect::<Vec<_>>();
+      params.sort();
+      format!("{params:?}")
+    };
+    let router = Router::builder(not_found)
+      .get("/items/:id(int)", |_| async { "int" })
+      .get("/items/:id(uuid)", |_| async { "uuid" })
+      .get("/items/:code([A-Z]{3})", |_| async { "code" })
+      .get("/items/:slug(slug)", |_| async { "slug" })
+      .get("/static/*path", show)
+      .build()
+      .unwrap();
+    let handler = RouterHandler::new(router);
+    for (path, body) in [
+      ("/items/-12", "int"),
+      ("/items/67e55044-10b1-426f-9247-bb680e5fe0c8", "uuid"),
+      ("/items/ABC", "code"),
+      ("/items/abc-def", "slug"),
+      ("/static/css/site.css", r#"[("path", "css/site.css")]"#),
+    ] {
+      let response = handler.call(request(RequestMethod::Get, path)).await;
+      assert_eq!(response.body().get_bytes(), body.as_bytes(), "{path}");
+    }
+    let response = handler
+      .call(request(RequestMethod::Get, "/items/a.b"))
+      .await;
+    assert_eq!(*response.status_code(), StatusCode::NotFound);
+
+    let errors = Router::builder(not_found)
+      .get("/a/:id(", |_| async { "" })
+      .get("/b/:id([)", |_| async { "" })
+      .get("/c/*rest/d", |_| async { "" })
+      .build()
+      .err()
+      .unwrap();
+    assert_eq!(errors.len(), 3);
+  }
 }
@@ -1,68 +1,477 @@
-use regex::Regex;
-
-use super::Route;
+use super::node::Node;
+use super::route::Target;
+use super::{MethodSet, Route, RouteError, Segment};
 use crate::handler::*;
 use crate::http::*;
+pub enum Lookup<'a> {
+  /// The handler to call, the parameters captured from the path and the prefix consumed by
+  /// nested routers on the way, which is empty unless the route was found in a nested router.
+  Found(
+    &'a (dyn Handler<Request, Response = Response> + Send),
+    Params,
+    String,
+  ),
+  /// Some route matched the path, but none with this method. Holds the methods that are allowed.
+  MethodNotAllowed(Vec<RequestMethod>),
+  /// Nothing matched. Holds the not-found handler of the innermost router the path reached.
+  NotFound(&'a (dyn Handler<Request, Response = Response> + Send)),
+}
+
+/// Why [`Router::url_for`] couldn't build a URL.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum UrlError {
+  #[error("no route is named `{0}`")]
+  UnknownRoute(String),
+  #[error("missing a value for the parameter `{0}`")]
+  MissingParam(String),
+  #[error("the route has no parameter `{0}`")]
+  ExtraParam(String),
+  #[error("the route has a `*` segment, which has no name to fill it in by")]
+  Wildcard,
+}
+
 pub struct Router {
   routes: Vec<Route>,
+  tree: Node,
   not_found: Box<dyn Handler<Request, Response = Response> + Send>,
 }
 impl Router {
   pub fn new<H: Handler<Request, Response = Response> + Send + 'static>(
     routes: Vec<Route>,
     not_found: H,
-  ) -> Self {
-    Self {
-      routes,
-      not_found: Box::new(not_found),
+  ) -> Result<Self, Vec<RouteError>> {
+    Self::from_parts(routes, Box::new(not_found))
+  }
+  /// Checks every route before indexing them, so all problems are reported at once.
+  fn from_parts(
+    routes: Vec<Route>,
+    not_found: Box<dyn Handler<Request, Response = Response> + Send>,
+  ) -> Result<Self, Vec<RouteError>> {
+    let mut errors = routes.iter().flat_map(Route::validate).collect::<Vec<_>>();
+    for (i, route) in routes.iter().enumerate() {
+      if let Some(name) = route.name() {
+        if routes[..i].iter().any(|r| r.name() == Some(name)) {
+          errors.push(RouteError::DuplicateName(name.into()));
+        }
+      }
+      // Routes with invalid patterns have already been reported, and have no segments to compare.
+      let valid = |r: &&Route| r.validate().is_empty();
+      let earlier = routes[..i].iter().filter(valid).collect::<Vec<_>>();
+      if let Some(earlier) = route.shadowed_by(&earlier).filter(|_| valid(&route)) {
+        errors.push(RouteError::Conflict(
+          earlier.pattern().into(),
+          route.pattern().into(),
+        ));
+      }
+    }
+    if !errors.is_empty() {
+      return Err(errors);
     }
+    let mut tree = Node::default();
+    for (i, route) in routes.iter().enumerate() {
+      for shape in route.shapes() {
+        tree.insert(&shape, i);
+      }
+    }
+    Ok(Self {
+      routes,
+      tree,
+      not_found,
+    })
   }
   pub fn builder<H: Handler<Request, Response = Response> + Send + 'static>(
     not_found: H,
   ) -> RouterBuilder {
     RouterBuilder::new(not_found)
   }
-  pub fn get_handler(
-    &mut self,
-    method: &RequestMethod,
-    path: &str,
-  ) -> Option<&mut (dyn Handler<Request, Response = Response> + Send)> {
-    self
-      .routes
-      .iter_mut()
-      .filter(|r| r.method() == method)
-      .find(|r| r.matches(path).is_some())
-      .map(move |r| r.handler())
+  /// Finds the most specific route matching `method` and `path`, along with the parameters
+  /// captured from the path. When the path matches but the method doesn't, the result lists the
+  /// methods that would have matched so the caller can answer 405 or OPTIONS. A path of `*`
+  /// matches nothing but collects the methods of every route, as `OPTIONS *` asks about the
+  /// server.
+  pub fn lookup(&self, method: &RequestMethod, path: &str) -> Lookup<'_> {
+    let mut allowed = vec![];
+    let mut found = None;
+    // HEAD is served by the most specific route that takes GET, so the headers match what a GET
+    // would get, unless a route with the same pattern lists HEAD itself.
+    let mut get_route: Option<(usize, Vec<String>)> = None;
+    let mut visit = |i: usize, captures: &[String]| {
+      let route = &self.routes[i];
+      let methods = route.methods();
+      let mut takes_method = methods.contains(method);
+      if let Some((get, _)) = &get_route {
+        if !self.routes[*get].same_shape(route) {
+          return true;
+        }
+        takes_method = matches!(methods, MethodSet::Only(methods) if methods.contains(method));
+      }
+      if path != "*" && takes_method {
+        found = Some((i, captures.to_vec()));
+        return true;
+      }
+      if path != "*"
+        && *method == RequestMethod::Head
+        && get_route.is_none()
+        && methods.contains(&RequestMethod::Get)
+      {
+        get_route = Some((i, captures.to_vec()));
+      }
+      if let MethodSet::Only(methods) = methods {
+        for method in methods {
+          if !allowed.contains(method) {
+            allowed.push(method.clone());
+          }
+        }
+      }
+      false
+    };
+    if path == "*" {
+      (0..self.routes.len()).for_each(|i| {
+        visit(i, &[]);
+      });
+    } else {
+      let segments = path.strip_prefix('/').unwrap_or(path).split('/');
+      let segments = segments.collect::<Vec<_>>();
+      self.tree.visit(&segments, &mut vec![], &mut visit);
+    }
+    if let Some((i, captures)) = found.or(get_route) {
+      let route = &self.routes[i];
+      let mut params = route.params(&captures);
+      // A nested router sees the path with the prefix cut off, which a catch-all captured
+      // unless the path ended with the prefix.
+      let prefix_captures = route
+        .segments()
+        .iter()
+        .filter(|s| !matches!(s, Segment::Static(_)));
+      let rest = (prefix_captures.count().checked_sub(1))
+        .and_then(|i| captures.get(i))
+        .map_or("", String::as_str);
+      return match route.target() {
+        Target::Handler(handler) => Lookup::Found(&**handler, params, String::new()),
+        Target::Router(router) => {
+          let prefix = path[..path.len() - rest.len()].trim_end_matches('/');
+          match router.lookup(method, &format!("/{rest}")) {
+            Lookup::Found(handler, inner, inner_prefix) => {
+              for (name, value) in inner.iter() {
+                params.insert(name, value);
+              }
+              Lookup::Found(handler, params, format!("{prefix}{inner_prefix}"))
+            }
+            other => other,
+          }
+        }
+      };
+    }
+    if allowed.is_empty() {
+      return Lookup::NotFound(&*self.not_found);
+    }
+    if allowed.contains(&RequestMethod::Get) && !allowed.contains(&RequestMethod::Head) {
+      allowed.push(RequestMethod::Head);
+    }
+    if !allowed.contains(&RequestMethod::Options) {
+      allowed.push(RequestMethod::Options);
+    }
+    Lookup::MethodNotAllowed(allowed)
   }
 
-  pub fn not_found(&mut self) -> &mut(dyn Handler<Request, Response = Response> + Send) {
-    &mut *self.not_found
+  /// Builds the path of the route called `name`, percent-encoding the parameter values, e.g.
+  /// `url_for("user", &[("id", "42")])` gives `/users/42` for a route `/users/:id`. Routes in
+  /// nested routers are found too, and their prefix's parameters are filled in from `params` as
+  /// well. Every parameter must be given, and nothing else.
+  pub fn url_for(&self, name: &str, params: &[(&str, &str)]) -> Result<String, UrlError> {
+    let segments = self
+      .named(name)
+      .ok_or_else(|| UrlError::UnknownRoute(name.into()))?;
+    let mut used = vec![];
+    let mut path = String::new();
+    for segment in &segments {
+      path.push('/');
+      match segment {
+        Segment::Static(s) => path.push_str(s),
+        Segment::Param(param, _) | Segment::CatchAll(Some(param)) => {
+          let value = params
+            .iter()
+            .find(|(k, _)| k == param)
+            .map(|(_, v)| *v)
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| UrlError::MissingParam(param.clone()))?;
+          // A catch-all's value spans segments, so its slashes are kept.
+          let value = match segment {
+            Segment::CatchAll(_) => value.split('/').map(percent_encode).collect::<Vec<_>>(),
+            _ => vec![percent_encode(value)],
+          };
+          path.push_str(&value.join("/"));
+          used.push(param.as_str());
+        }
+        Segment::Wildcard | Segment::CatchAll(None) => return Err(UrlError::Wildcard),
+      }
+    }
+    if let Some((extra, _)) = params.iter().find(|(k, _)| !used.contains(k)) {
+      return Err(UrlError::ExtraParam(extra.to_string()));
+    }
+    if path.is_empty() {
+      path.push('/');
+    }
+    Ok(path)
+  }
+  /// The segments of the route called `name`, nested routers' prefixes included.
+  fn named(&self, name: &str) -> Option<Vec<Segment>> {
+    self.routes.iter().find_map(|route| match route.target() {
+      Target::Handler(_) => (route.name() == Some(name)).then(|| route.segments().to_vec()),
+      Target::Router(router) => {
+        let mut segments = router.named(name)?;
+        let prefix = &route.segments()[..route.segments().len() - 1];
+        segments.splice(..0, prefix.iter().cloned());
+        Some(segments)
+      }
+    })
+  }
+
+  pub fn not_found(&self) -> &(dyn Handler<Request, Response = Response> + Send) {
+    &*self.not_found
   }
 }
 pub struct RouterBuilder {
   routes: Vec<Route>,
+  errors: Vec<RouteError>,
   not_found: Box<dyn Handler<Request, Response = Response> + Send>,
 }
 impl RouterBuilder {
   pub fn new<H: Handler<Request, Response = Response> + Send + 'static>(not_found: H) -> Self {
     Self {
       routes: vec![],
+      errors: vec![],
       not_found: Box::new(not_found),
     }
   }
-  pub fn get<H: Handler<Request, Response = Response> + Send + 'static>(
-    mut self,
-    path: &str,
-    handler: H,
-  ) -> Self {
-    let route = Route::new(RequestMethod::Get, path, handler);
-    self.routes.push(route);
+  /// Registers one handler for several methods, e.g.
+  /// `route("/items", [RequestMethod::Put, RequestMethod::Patch], update)`.
+  pub fn route<H>(mut self, path: &str, methods: impl Into<MethodSet>, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.routes.push(Route::new(methods, path, handler));
     self
   }
-  pub fn build(mut self) -> Router {
-    Router {
-      routes: self.routes,
-      not_found: self.not_found,
+  pub fn get<H>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.route(path, RequestMethod::Get, handler)
+  }
+  pub fn post<H>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.route(path, RequestMethod::Post, handler)
+  }
+  pub fn put<H>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.route(path, RequestMethod::Put, handler)
+  }
+  pub fn patch<H>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.route(path, RequestMethod::Patch, handler)
+  }
+  pub fn delete<H>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.route(path, RequestMethod::Delete, handler)
+  }
+  pub fn head<H>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.route(path, RequestMethod::Head, handler)
+  }
+  pub fn options<H>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.route(path, RequestMethod::Options, handler)
+  }
+  /// Matches every method, including extension methods.
+  pub fn any<H>(self, path: &str, handler: H) -> Self
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.route(path, MethodSet::Any, handler)
+  }
+  /// Names the route added last, e.g. `.get("/users/:id", show_user).name("user")`, so
+  /// [`Router::url_for`] can build links to it.
+  pub fn name(mut self, name: &str) -> Self {
+    match self.routes.pop() {
+      Some(route) => self.routes.push(route.named(name)),
+      None => self.errors.push(RouteError::NameWithoutRoute(name.into())),
+    }
+    self
+  }
+  /// Mounts `router` under `prefix`, e.g. `nest("/api/v1", api)`. The nested router matches
+  /// the rest of the path and answers with its own not-found handler when nothing in it matches.
+  /// Parameters in the prefix are merged with those of the nested route.
+  pub fn nest(mut self, prefix: &str, router: Router) -> Self {
+    self.routes.push(Route::nested(prefix, router));
+    self
+  }
+  /// Fails with every invalid pattern, duplicate parameter name and conflicting route found.
+  pub fn build(mut self) -> Result<Router, Vec<RouteError>> {
+    match Router::from_parts(self.routes, self.not_found) {
+      Ok(router) if self.errors.is_empty() => Ok(router),
+      Ok(_) => Err(self.errors),
+      Err(errors) => {
+        self.errors.extend(errors);
+        Err(self.errors)
+      }
+    }
+  }
+}
+
+/// Escapes everything but unreserved characters (RFC 3986, section 2.3), so a value can't
+/// change the structure of the path it's put into.
+fn percent_encode(value: &str) -> String {
+  let mut out = String::new();
+  for b in value.bytes() {
+    if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+      out.push(b as char);
+    } else {
+      out.push_str(&format!("%{b:02X}"));
     }
   }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  async fn ok(_: Request) -> Response {
+    StatusCode::Ok.into_response()
+  }
+
+  #[test]
+  fn build_reports_every_problem() {
+    let errors = Router::builder(ok)
+      .get("no-slash", ok)
+      .get("/a/:/b", ok)
+      .get("/files/*.txt", ok)
+      .get("/users/:id/posts/:id", ok)
+      .get("/users/:id", ok)
+      .route(
+        "/users/:name",
+        [RequestMethod::Get, RequestMethod::Post],
+        ok,
+      )
+      .post("/users/:uid", ok)
+      .post("/users/:id/x", ok)
+      .get("/a/*/b", ok)
+      .get("/a/:x/b", ok)
+      .get("/a/:n(int)/b", ok)
+      // Other methods still reach the second route.
+      .get("/x", ok)
+      .any("/x", ok)
+      .nest("/api", Router::builder(ok).get("/", ok).build().unwrap())
+      .get("/api", ok)
+      .post("/api/", ok)
+      .build()
+      .err()
+      .unwrap();
+    assert_eq!(
+      errors,
+      [
+        RouteError::InvalidPattern("no-slash".into(), "must start with `/`".into()),
+        RouteError::InvalidPattern("/a/:/b".into(), "parameter without a name".into()),
+        RouteError::InvalidPattern(
+          "/files/*.txt".into(),
+          "invalid parameter name `.txt`".into()
+        ),
+        RouteError::DuplicateParam("/users/:id/posts/:id".into(), "id".into()),
+        RouteError::Conflict("/users/:name".into(), "/users/:uid".into()),
+        RouteError::Conflict("/a/*/b".into(), "/a/:x/b".into()),
+        RouteError::Conflict("/api".into(), "/api".into()),
+        RouteError::Conflict("/api".into(), "/api/".into()),
+      ]
+    );
+  }
+
+  #[test]
+  fn literal_segments_match_exactly() {
+    let mut router = Router::builder(ok).get("/file.txt", ok).build().unwrap();
+    assert!(matches!(
+      router.lookup(&RequestMethod::Get, "/file.txt"),
+      Lookup::Found(..)
+    ));
+    assert!(matches!(
+      router.lookup(&RequestMethod::Get, "/fileXtxt"),
+      Lookup::NotFound(_)
+    ));
+  }
+
+  #[test]
+  fn urls_are_built_from_named_routes() {
+    let users = Router::builder(ok)
+      .get("/", ok)
+      .name("users")
+      .get("/:id/files/:file", ok)
+      .name("file")
+      .build()
+      .unwrap();
+    let router = Router::builder(ok)
+      .get("/", ok)
+      .name("home")
+      .nest("/orgs/:org/users", users)
+      .build()
+      .unwrap();
+    assert_eq!(router.url_for("home", &[]), Ok("/".into()));
+    assert_eq!(
+      router.url_for("users", &[("org", "acme")]),
+      Ok("/orgs/acme/users/".into())
+    );
+    assert_eq!(
+      router.url_for("file", &[("org", "a b"), ("id", "7"), ("file", "x/y?")]),
+      Ok("/orgs/a%20b/users/7/files/x%2Fy%3F".into())
+    );
+    assert_eq!(
+      router.url_for("file", &[("org", "a"), ("id", "7")]),
+      Err(UrlError::MissingParam("file".into()))
+    );
+    assert_eq!(
+      router.url_for("home", &[("id", "7")]),
+      Err(UrlError::ExtraParam("id".into()))
+    );
+    assert_eq!(
+      router.url_for("nope", &[]),
+      Err(UrlError::UnknownRoute("nope".into()))
+    );
+
+    let errors = Router::builder(ok)
+      .name("early")
+      .get("/a", ok)
+      .name("a")
+      .get("/b", ok)
+      .name("a")
+      .build()
+      .err()
+      .unwrap();
+    assert_eq!(
+      errors,
+      [
+        RouteError::NameWithoutRoute("early".into()),
+        RouteError::DuplicateName("a".into())
+      ]
+    );
+  }
 }
@@ -0,0 +1,204 @@
+use std::io;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+use std::time::Duration;
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use super::{Config, Server};
+use crate::error::*;
+use crate::http::*;
+use crate::parse::Limits;
+
+enum Bind {
+  Addr(SocketAddr),
+  Listener(TcpListener),
+}
+
+/// Configures a [`Server`]. Addresses are resolved as they are added, but nothing is bound until
+/// [`ServerBuilder::build`].
+pub struct ServerBuilder {
+  binds: Vec<Bind>,
+  error: Option<Error>,
+  backlog: u32,
+  reuse_port: bool,
+  nodelay: bool,
+  tcp_keepalive: Option<Duration>,
+  #[cfg(feature = "tls")]
+  tls: Option<super::Tls>,
+  config: Config,
+}
+
+impl ServerBuilder {
+  pub fn new() -> Self {
+    Self {
+      binds: vec![],
+      error: None,
+      backlog: 1024,
+      reuse_port: false,
+      nodelay: false,
+      tcp_keepalive: None,
+      #[cfg(feature = "tls")]
+      tls: None,
+      config: Config::default(),
+    }
+  }
+  /// Listens on `addr`, e.g. a [`SocketAddr`] or a string like `"0.0.0.0:8080"`. Can be called
+  /// more than once, and a name resolving to several addresses listens on all of them.
+  pub fn bind(mut self, addr: impl ToSocketAddrs) -> Self {
+    match addr.to_socket_addrs() {
+      Ok(addrs) => self.binds.extend(addrs.map(Bind::Addr)),
+      Err(e) => {
+        self.error.get_or_insert(Error::new_io(e));
+      }
+    }
+    self
+  }
+  /// Accepts connections from a listener that is already bound, e.g. one handed over by a
+  /// process manager. Socket options that apply when binding are left as they are.
+  pub fn listener(mut self, listener: TcpListener) -> Self {
+    self.binds.push(Bind::Listener(listener));
+    self
+  }
+  /// How many connections the OS queues before they are accepted. 1024 unless set.
+  pub fn backlog(mut self, backlog: u32) -> Self {
+    self.backlog = backlog;
+    self
+  }
+  /// Sets `SO_REUSEPORT` so several processes can listen on the same port. Only has an effect on
+  /// Unix.
+  pub fn reuse_port(mut self, enabled: bool) -> Self {
+    self.reuse_port = enabled;
+    self
+  }
+  /// Sets `TCP_NODELAY` on accepted connections, sending small writes without waiting to batch
+  /// them.
+  pub fn nodelay(mut self, enabled: bool) -> Self {
+    self.nodelay = enabled;
+    self
+  }
+  /// Serves HTTPS on every listener.
+  #[cfg(feature = "tls")]
+  pub fn tls(mut self, tls: super::Tls) -> Self {
+    self.tls = Some(tls);
+    self
+  }
+  /// Sends TCP keepalive probes after a connection has been silent for `idle`. Off unless set.
+  pub fn tcp_keepalive(mut self, idle: Duration) -> Self {
+    self.tcp_keepalive = Some(idle);
+    self
+  }
+  /// Allow clients to send more than one request per connection. Enabled by default.
+  pub fn keep_alive(mut self, enabled: bool) -> Self {
+    self.config.keep_alive = enabled;
+    self
+  }
+  /// How long an idle persistent connection is kept open waiting for the next request.
+  pub fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+    self.config.keep_alive_timeout = timeout;
+    self
+  }
+  /// How long writing each part of a response may take before the client is given up on.
+  pub fn write_timeout(mut self, timeout: Duration) -> Self {
+    self.config.write_timeout = timeout;
+    self
+  }
+  /// How long a client may take to send the request line and headers. Clients that trickle them
+  /// in slower get a 408 Request Timeout and are disconnected.
+  pub fn header_read_timeout(mut self, timeout: Duration) -> Self {
+    self.config.header_read_timeout = timeout;
+    self
+  }
+  /// How long a client may take to send a request body, answered like a header timeout.
+  pub fn body_read_timeout(mut self, timeout: Duration) -> Self {
+    self.config.body_read_timeout = timeout;
+    self
+  }
+  /// Most connections served at once. Once reached, no more are accepted until one closes.
+  pub fn max_connections(mut self, max: usize) -> Self {
+    self.config.max_connections = max;
+    self
+  }
+  /// How long requests in progress get to finish when the server shuts down. Connections still
+  /// open after it are dropped.
+  pub fn grace_period(mut self, grace: Duration) -> Self {
+    self.config.grace_period = grace;
+    self
+  }
+  /// Largest request body accepted, in bytes. Bigger requests get a 413 Payload Too Large.
+  pub fn max_body_size(mut self, bytes: usize) -> Self {
+    self.config.max_body_size = bytes;
+    self
+  }
+  /// Caps on the request line and headers. Requests over them are rejected before the handler
+  /// runs.
+  pub fn limits(mut self, limits: Limits) -> Self {
+    self.config.limits = limits;
+    self
+  }
+  /// Replaces the default mapping from malformed requests to error responses. The default is
+  /// [`Error::into_response`], which callers can fall back to for errors they don't care about.
+  pub fn on_error<F>(mut self, handler: F) -> Self
+  where
+    F: Fn(Error) -> Response + Send + Sync + 'static,
+  {
+    self.config.error_handler = Arc::new(handler);
+    self
+  }
+  /// Binds every address. Fails with a [`Kind::Io`] error for the first address that couldn't
+  /// be resolved or bound, when no address was given, or when no connections are allowed.
+  pub fn build(self) -> Result<Server, Error> {
+    if let Some(err) = self.error {
+      return Err(err);
+    }
+    if self.config.max_connections == 0 {
+      let err = io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "max_connections must be at least 1",
+      );
+      return Err(Error::new_io(err));
+    }
+    if self.binds.is_empty() {
+      let err = io::Error::new(io::ErrorKind::InvalidInput, "no address to listen on");
+      return Err(Error::new_io(err));
+    }
+    let mut listeners = vec![];
+    for bind in self.binds {
+      let listener = match bind {
+        Bind::Addr(addr) => listen(addr, self.backlog, self.reuse_port).map_err(|e| {
+          let err = io::Error::new(e.kind(), format!("couldn't bind {addr}: {e}"));
+          Error::new_io(err)
+        })?,
+        Bind::Listener(listener) => listener,
+      };
+      // The runtime needs non-blocking sockets.
+      listener.set_nonblocking(true).map_err(Error::new_io)?;
+      listeners.push(listener);
+    }
+    Ok(Server {
+      listeners,
+      nodelay: self.nodelay,
+      tcp_keepalive: self.tcp_keepalive,
+      #[cfg(feature = "tls")]
+      tls: self.tls,
+      config: self.config,
+    })
+  }
+}
+
+impl Default for ServerBuilder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+fn listen(addr: SocketAddr, backlog: u32, reuse_port: bool) -> io::Result<TcpListener> {
+  let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+  // Lets a restarted server bind while connections from the old one are in TIME_WAIT.
+  socket.set_reuse_address(true)?;
+  #[cfg(unix)]
+  socket.set_reuse_port(reuse_port)?;
+  socket.bind(&addr.into())?;
+  socket.listen(backlog.try_into().unwrap_or(i32::MAX))?;
+  Ok(socket.into())
+}
@@ -0,0 +1,443 @@
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::watch;
+use tokio::time;
+
+use super::Config;
+use crate::error::*;
+use crate::http::*;
+use crate::parse::{ChunkedDecoder, Framing, Parser, Status};
+use crate::Handler;
+
+/// A single client connection. Requests are read off the stream one after another and answered
+/// in the order they arrived, so pipelined requests get their responses in order.
+pub struct Connection<S> {
+  stream: S,
+  buf: Vec<u8>,
+  config: Config,
+  shutdown: Option<watch::Receiver<bool>>,
+}
+impl<S> Connection<S>
+where
+  S: AsyncRead + AsyncWrite + Unpin,
+{
+  pub fn new(stream: S, config: Config) -> Self {
+    Self {
+      stream,
+      buf: Vec::new(),
+      config,
+      shutdown: None,
+    }
+  }
+  /// Winds the connection down once `shutdown` turns true: the request in progress is finished
+  /// and answered with `Connection: close`, and an idle connection is closed right away.
+  pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+    self.shutdown = Some(shutdown);
+    self
+  }
+  pub async fn serve<H>(mut self, handler: Arc<H>) -> Result<(), Error>
+  where
+    H: Handler<Request> + Send,
+    H::Response: IntoResponse,
+  {
+    loop {
+      let request = match self.read_request().await {
+        Ok(Some(request)) => request,
+        Ok(None) => return Ok(()),
+        // Nothing can be written back on a broken socket.
+        Err(e) if matches!(e.kind(), Kind::Io) => return Err(e),
+        Err(e) => {
+          // The rest of the request is still on the wire, so the connection can't be reused.
+          let response = (self.config.error_handler)(e);
+          self
+            .write_response(response, Version::Http11, false, false)
+            .await?;
+          return self.stream.shutdown().await.map_err(Error::new_io);
+        }
+      };
+      let version = request.version();
+      let head = *request.method() == RequestMethod::Head;
+      let keep_alive = self.config.keep_alive && request.keep_alive();
+      let response = handler.call(request).await.into_response();
+      let keep_alive = keep_alive && !self.is_shutting_down();
+      if !self
+        .write_response(response, version, keep_alive, head)
+        .await?
+      {
+        return self.stream.shutdown().await.map_err(Error::new_io);
+      }
+    }
+  }
+  /// Writes a response, streaming the body out as it is produced when its size isn't known.
+  /// Responses to HEAD carry the headers a GET would have, `Content-Length` included, but no
+  /// body, and 1xx, 204 and 304 responses never have one. Returns whether the connection can be
+  /// reused afterwards.
+  async fn write_response(
+    &mut self,
+    mut response: Response,
+    version: Version,
+    mut keep_alive: bool,
+    head: bool,
+  ) -> Result<bool, Error> {
+    let allows_body = response.status_code().allows_body();
+    let streaming =
+      allows_body && response.body().is_stream() && !response.headers().contains("Content-Length");
+    let chunked = streaming && version == Version::Http11;
+    // HTTP/1.0 clients don't understand chunked encoding, so closing the connection is the only
+    // way left to mark the end of the body.
+    keep_alive &= head || !streaming || chunked;
+    if chunked {
+      response
+        .headers_mut()
+        .insert("Transfer-Encoding", "chunked")
+        .unwrap();
+    }
+    if !keep_alive {
+      response
+        .headers_mut()
+        .insert("Connection", "close")
+        .unwrap();
+    }
+    if head || !allows_body {
+      if let Some(len) = response.body().content_length() {
+        if head && allows_body && !response.headers().contains("Content-Length") {
+          response
+            .headers_mut()
+            .insert("Content-Length", len.to_string())
+            .unwrap();
+        }
+      }
+      self.write(&response.head_bytes()).await?;
+      return Ok(keep_alive);
+    }
+    if !response.body().is_stream() {
+      self.write(&response.as_bytes()).await?;
+      return Ok(keep_alive);
+    }
+    self.write(&response.head_bytes()).await?;
+    while let Some(chunk) = response.body_mut().next_chunk().await {
+      if chunked {
+        let size = format!("{:x}\r\n", chunk.len());
+        self
+          .write(&[size.as_bytes(), &chunk, b"\r\n"].concat())
+          .await?;
+      } else {
+        self.write(&chunk).await?;
+      }
+    }
+    if chunked {
+      self.write(b"0\r\n\r\n").await?;
+    }
+    Ok(keep_alive)
+  }
+  async fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+    let write = async {
+      self.stream.write_all(bytes).await?;
+      self.stream.flush().await
+    };
+    match time::timeout(self.config.write_timeout, write).await {
+      Ok(written) => written.map_err(Error::new_io),
+      Err(_) => Err(Error::new_io(std::io::ErrorKind::TimedOut.into())),
+    }
+  }
+  /// Reads the next request off the connection. Returns `None` when the client closed the
+  /// connection or stayed idle longer than the keep-alive timeout between requests. Once a
+  /// request has started, its headers and then its body each have to arrive within their own
+  /// timeout, so a client can't hold the connection by sending a byte at a time.
+  async fn read_request(&mut self) -> Result<Option<Request>, Error> {
+    let mut parser = Parser::new(self.config.limits.clone());
+    let mut deadline = None;
+    let mut request = loop {
+      let idle = self.buf.is_empty();
+      if let Status::Complete(request) = parser.parse(&mut self.buf)? {
+        break request;
+      }
+      let n = if idle {
+        let shutdown = shutting_down(self.shutdown.clone());
+        tokio::select! {
+          read = time::timeout(self.config.keep_alive_timeout, self.fill_buf()) => match read {
+            Ok(n) => n?,
+            Err(_) => return Ok(None),
+          },
+          _ = shutdown => return Ok(None),
+        }
+      } else {
+        let deadline =
+          *deadline.get_or_insert_with(|| time::Instant::now() + self.config.header_read_timeout);
+        self.fill_buf_by(deadline).await?
+      };
+      if n == 0 {
+        return if idle {
+          Ok(None)
+        } else {
+          Err(unexpected_eof())
+        };
+      }
+    };
+
+    let deadline = time::Instant::now() + self.config.body_read_timeout;
+    match Framing::of(&request)? {
+      Framing::Length(len) => {
+        if len > self.config.max_body_size {
+          return Err(Error::new(Kind::PayloadTooLarge));
+        }
+        self.buf.reserve(len.saturating_sub(self.buf.len()));
+        while self.buf.len() < len {
+          if self.fill_buf_by(deadline).await? == 0 {
+            return Err(unexpected_eof());
+          }
+        }
+        let body = self.buf.drain(..len).collect::<Vec<_>>();
+        *request.body_mut() = body.into();
+      }
+      Framing::Chunked => {
+        let mut decoder = ChunkedDecoder::new(self.config.max_body_size);
+        loop {
+          let n = decoder.decode(&self.buf)?;
+          self.buf.drain(..n);
+          if decoder.is_done() {
+            break;
+          }
+          if self.fill_buf_by(deadline).await? == 0 {
+            return Err(unexpected_eof());
+          }
+        }
+        let (body, trailers) = decoder.finish();
+        *request.body_mut() = body.into();
+        *request.trailers_mut() = trailers;
+      }
+    }
+    Ok(Some(request))
+  }
+  async fn fill_buf(&mut self) -> Result<usize, Error> {
+    self.buf.reserve(READ_CHUNK);
+    self
+      .stream
+      .read_buf(&mut self.buf)
+      .await
+      .map_err(Error::new_io)
+  }
+  fn is_shutting_down(&self) -> bool {
+    self.shutdown.as_ref().is_some_and(|s| *s.borrow())
+  }
+  async fn fill_buf_by(&mut self, deadline: time::Instant) -> Result<usize, Error> {
+    time::timeout_at(deadline, self.fill_buf())
+      .await
+      .map_err(|_| Error::new(Kind::Timeout))?
+  }
+}
+
+const READ_CHUNK: usize = 4096;
+
+/// Resolves once `shutdown` turns true. Never resolves without a signal to wait on.
+async fn shutting_down(shutdown: Option<watch::Receiver<bool>>) {
+  if let Some(mut shutdown) = shutdown {
+    if shutdown.wait_for(|s| *s).await.is_ok() {
+      return;
+    }
+  }
+  std::future::pending().await
+}
+
+fn unexpected_eof() -> Error {
+  Error::new_io(std::io::ErrorKind::UnexpectedEof.into())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use tokio::io::duplex;
+
+  use super::*;
+
+  async fn echo_path(req: Request) -> Response {
+    Response::builder().body(req.uri().path()).unwrap()
+  }
+
+  #[tokio::test]
+  async fn pipelined_requests_are_answered_in_order() {
+    let (mut client, server) = duplex(4096);
+    let connection = Connection::new(server, Config::default());
+    let task = tokio::spawn(connection.serve(Arc::new(echo_path)));
+    client
+      .write_all(b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\nConnection: close\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    client.read_to_string(&mut out).await.unwrap();
+    task.await.unwrap().unwrap();
+    let a = out.find("\r\n\r\n/a").unwrap();
+    let b = out.find("\r\n\r\n/b").unwrap();
+    assert!(a < b);
+    assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 2);
+    assert!(out.contains("Connection: close"));
+  }
+
+  #[tokio::test]
+  async fn head_responses_have_no_body() {
+    let (mut client, server) = duplex(4096);
+    let connection = Connection::new(server, Config::default());
+    let task = tokio::spawn(connection.serve(Arc::new(echo_path)));
+    client
+      .write_all(b"HEAD /abc HTTP/1.1\r\n\r\nGET /d HTTP/1.1\r\nConnection: close\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    client.read_to_string(&mut out).await.unwrap();
+    task.await.unwrap().unwrap();
+    let (head, get) = out.split_once("\r\n\r\n").unwrap();
+    assert!(head.contains("Content-Length: 4"));
+    assert!(get.starts_with("HTTP/1.1 200 OK") && get.ends_with("\r\n\r\n/d"));
+  }
+
+  async fn body_len(req: Request) -> Response {
+    Response::builder()
+      .body(req.body().get_bytes().len().to_string())
+      .unwrap()
+  }
+
+  #[tokio::test]
+  async fn body_is_read_up_to_content_length() {
+    let (mut client, server) = duplex(1024);
+    let connection = Connection::new(server, Config::default());
+    let task = tokio::spawn(connection.serve(Arc::new(body_len)));
+    let body = vec![b'x'; 10_000];
+    let head = "POST / HTTP/1.1\r\nContent-Length: 10000\r\nConnection: close\r\n\r\n";
+    client.write_all(head.as_bytes()).await.unwrap();
+    client.write_all(&body).await.unwrap();
+    let mut out = String::new();
+    client.read_to_string(&mut out).await.unwrap();
+    task.await.unwrap().unwrap();
+    assert!(out.ends_with("\r\n\r\n10000"));
+  }
+
+  #[tokio::test]
+  async fn oversized_body_is_rejected() {
+    let (mut client, server) = duplex(1024);
+    let config = Config {
+      max_body_size: 16,
+      ..Config::default()
+    };
+    let task = tokio::spawn(Connection::new(server, config).serve(Arc::new(body_len)));
+    client
+      .write_all(b"POST / HTTP/1.1\r\nContent-Length: 17\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    client.read_to_string(&mut out).await.unwrap();
+    task.await.unwrap().unwrap();
+    assert!(out.starts_with("HTTP/1.1 413 Content Too Large\r\n"));
+  }
+
+  async fn countdown(_: Request) -> Response {
+    let (tx, body) = Body::channel(1);
+    tokio::spawn(async move {
+      for i in (1..=3).rev() {
+        tx.send(i.to_string()).await.unwrap();
+      }
+    });
+    Response::builder().body(body).unwrap()
+  }
+
+  #[tokio::test]
+  async fn streaming_bodies_are_chunked() {
+    let (mut client, server) = duplex(1024);
+    let task = tokio::spawn(Connection::new(server, Config::default()).serve(Arc::new(countdown)));
+    client
+      .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    client.read_to_string(&mut out).await.unwrap();
+    task.await.unwrap().unwrap();
+    assert!(out.contains("Transfer-Encoding: chunked\r\n"));
+    assert!(!out.contains("Content-Length"));
+    assert!(out.ends_with("\r\n\r\n1\r\n3\r\n1\r\n2\r\n1\r\n1\r\n0\r\n\r\n"));
+  }
+
+  async fn streamed_no_content(_: Request) -> Response {
+    let (tx, body) = Body::channel(1);
+    tokio::spawn(async move { tx.send("x").await });
+    Response::builder().status(204).body(body).unwrap()
+  }
+
+  #[tokio::test]
+  async fn bodiless_statuses_drop_streamed_bodies() {
+    let (mut client, server) = duplex(1024);
+    let connection = Connection::new(server, Config::default());
+    let task = tokio::spawn(connection.serve(Arc::new(streamed_no_content)));
+    client
+      .write_all(b"GET / HTTP/1.1\r\n\r\nGET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    client.read_to_string(&mut out).await.unwrap();
+    task.await.unwrap().unwrap();
+    // Nothing may follow the first head, or the client would read it as the second response.
+    assert_eq!(
+      out,
+      "HTTP/1.1 204 No Content\r\n\r\nHTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n"
+    );
+  }
+
+  #[tokio::test]
+  async fn slow_clients_time_out() {
+    let config = Config {
+      header_read_timeout: Duration::from_millis(50),
+      body_read_timeout: Duration::from_millis(50),
+      ..Config::default()
+    };
+    for partial in [
+      &b"GET / HTTP/1.1\r\nHost: a"[..],
+      b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nab",
+    ] {
+      let (mut client, server) = duplex(1024);
+      let connection = Connection::new(server, config.clone());
+      let task = tokio::spawn(connection.serve(Arc::new(body_len)));
+      client.write_all(partial).await.unwrap();
+      let mut out = String::new();
+      client.read_to_string(&mut out).await.unwrap();
+      task.await.unwrap().unwrap();
+      assert!(out.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+    }
+  }
+
+  #[tokio::test]
+  async fn shutdown_closes_idle_connections() {
+    let (mut client, server) = duplex(1024);
+    let (shutdown, signal) = watch::channel(false);
+    let connection = Connection::new(server, Config::default()).with_shutdown(signal);
+    let task = tokio::spawn(connection.serve(Arc::new(echo_path)));
+    client.write_all(b"GET /a HTTP/1.1\r\n\r\n").await.unwrap();
+    let mut buf = [0; 1024];
+    let n = client.read(&mut buf).await.unwrap();
+    assert!(!String::from_utf8_lossy(&buf[..n]).contains("Connection: close"));
+    shutdown.send(true).unwrap();
+    let mut out = String::new();
+    client.read_to_string(&mut out).await.unwrap();
+    task.await.unwrap().unwrap();
+    assert!(out.is_empty());
+  }
+
+  #[tokio::test]
+  async fn malformed_requests_get_an_error_response() {
+    let (mut client, server) = duplex(1024);
+    let config = Config {
+      error_handler: Arc::new(|e: Error| match e.kind() {
+        Kind::UnsupportedVersion => Response::builder().status(400).body("upgrade").unwrap(),
+        _ => e.into_response(),
+      }),
+      ..Config::default()
+    };
+    let task = tokio::spawn(Connection::new(server, config).serve(Arc::new(echo_path)));
+    client.write_all(b"GET / HTTP/2.0\r\n\r\n").await.unwrap();
+    let mut out = String::new();
+    client.read_to_string(&mut out).await.unwrap();
+    task.await.unwrap().unwrap();
+    assert!(out.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+    assert!(out.contains("Connection: close\r\n"));
+    assert!(out.ends_with("upgrade"));
+  }
+}
@@ -0,0 +1,314 @@
+use std::future::{poll_fn, Future};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::Duration;
+
+use socket2::{SockRef, TcpKeepalive};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net;
+use tokio::sync::{watch, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{self, Instant};
+
+use crate::error::*;
+use crate::http::*;
+use crate::parse::Limits;
+use crate::Handler;
+
+mod builder;
+mod connection;
+#[cfg(feature = "tls")]
+mod tls;
+pub use builder::ServerBuilder;
+pub use connection::Connection;
+#[cfg(feature = "tls")]
+pub use tls::{Tls, TlsBuilder};
+
+/// Builds the response sent when a request can't be read, right before the connection closes.
+pub type ErrorHandler = Arc<dyn Fn(Error) -> Response + Send + Sync>;
+
+/// Settings shared by every connection accepted by a [`Server`].
+#[derive(Clone)]
+pub struct Config {
+  pub keep_alive: bool,
+  pub keep_alive_timeout: Duration,
+  /// Time allowed for each write of a response to the client.
+  pub write_timeout: Duration,
+  /// Time allowed from the first byte of a request to the end of its headers.
+  pub header_read_timeout: Duration,
+  /// Time allowed to receive a request body once the headers are in.
+  pub body_read_timeout: Duration,
+  /// Connections served at once. Further clients wait in the listen backlog until one closes.
+  pub max_connections: usize,
+  /// Time open connections get to finish after shutdown starts before they are dropped.
+  pub grace_period: Duration,
+  pub max_body_size: usize,
+  pub limits: Limits,
+  pub error_handler: ErrorHandler,
+}
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      keep_alive: true,
+      keep_alive_timeout: Duration::from_secs(5),
+      write_timeout: Duration::from_secs(30),
+      header_read_timeout: Duration::from_secs(10),
+      body_read_timeout: Duration::from_secs(30),
+      max_connections: 1024,
+      grace_period: Duration::from_secs(30),
+      max_body_size: 2 * 1024 * 1024,
+      limits: Limits::default(),
+      error_handler: Arc::new(Error::into_response),
+    }
+  }
+}
+
+/// An HTTP server bound to one or more listeners. Made with [`Server::builder`].
+pub struct Server {
+  listeners: Vec<std::net::TcpListener>,
+  nodelay: bool,
+  tcp_keepalive: Option<Duration>,
+  #[cfg(feature = "tls")]
+  tls: Option<Tls>,
+  config: Config,
+}
+impl Server {
+  pub fn builder() -> ServerBuilder {
+    ServerBuilder::new()
+  }
+  /// The addresses the server is listening on, e.g. to find the port picked for port 0.
+  pub fn local_addrs(&self) -> Vec<SocketAddr> {
+    self
+      .listeners
+      .iter()
+      .filter_map(|l| l.local_addr().ok())
+      .collect()
+  }
+  /// Serves connections until SIGINT or SIGTERM, then shuts down as [`Server::listen_until`]
+  /// does.
+  pub async fn listen<H>(self, handler: H) -> Result<Shutdown, Error>
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+  {
+    self.listen_until(handler, shutdown_signal()).await
+  }
+  /// Serves connections until `shutdown` resolves. The server then stops accepting, closes idle
+  /// connections, and lets requests in progress finish for up to the grace period before
+  /// dropping whatever is still open.
+  pub async fn listen_until<H, F>(self, handler: H, shutdown: F) -> Result<Shutdown, Error>
+  where
+    H: Handler<Request> + Send + 'static,
+    H::Response: IntoResponse,
+    F: Future<Output = ()>,
+  {
+    let listeners = self
+      .listeners
+      .iter()
+      .map(|l| net::TcpListener::from_std(l.try_clone()?))
+      .collect::<io::Result<Vec<_>>>()
+      .map_err(Error::new_io)?;
+    let handler = Arc::new(handler);
+    let permits = Arc::new(Semaphore::new(self.config.max_connections));
+    let (notify, shutting_down) = watch::channel(false);
+    let mut connections = JoinSet::new();
+    let mut shutdown = pin!(shutdown);
+    // Listeners are polled starting after the one that last had a connection, so a busy listener
+    // can't starve the others.
+    let mut next = 0;
+    #[cfg(feature = "tls")]
+    let reload = self.tls.clone().map(|tls| tokio::spawn(tls.watch()));
+    loop {
+      // Waiting for a free slot before accepting leaves new clients queued in the backlog.
+      let accept = async {
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        let accepted = poll_fn(|cx| {
+          for i in (0..listeners.len()).map(|k| (next + k) % listeners.len()) {
+            if let Poll::Ready(accepted) = listeners[i].poll_accept(cx) {
+              next = i + 1;
+              return Poll::Ready(accepted);
+            }
+          }
+          Poll::Pending
+        });
+        (permit, accepted.await)
+      };
+      let (permit, stream) = tokio::select! {
+        _ = &mut shutdown => break,
+        // Reaping finished connections keeps the set down to the open ones.
+        Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+        (permit, accepted) = accept => match accepted {
+          Ok((stream, _)) => (permit, stream),
+          Err(_) => {
+            eprintln!("couldn't accept stream from the listener");
+            continue;
+          }
+        },
+      };
+      if let Err(e) = self.configure(&stream) {
+        eprintln!("couldn't set socket options: {e}");
+      }
+      let config = self.config.clone();
+      let shutting_down = shutting_down.clone();
+      let handler = handler.clone();
+      #[cfg(feature = "tls")]
+      let tls = self.tls.clone();
+      connections.spawn(async move {
+        // The handshake happens here rather than in the accept loop so a slow client only holds
+        // up its own connection.
+        #[cfg(feature = "tls")]
+        let result = match tls {
+          Some(tls) => match tls.accept(stream, config.header_read_timeout).await {
+            Ok(stream) => serve(stream, config, shutting_down, handler).await,
+            Err(e) => Err(e),
+          },
+          None => serve(stream, config, shutting_down, handler).await,
+        };
+        #[cfg(not(feature = "tls"))]
+        let result = serve(stream, config, shutting_down, handler).await;
+        if let Err(e) = result {
+          eprintln!("connection error: {e}");
+        }
+        drop(permit);
+      });
+    }
+
+    drop(listeners);
+    drop(self.listeners);
+    #[cfg(feature = "tls")]
+    if let Some(reload) = reload {
+      reload.abort();
+    }
+    let started = Instant::now();
+    let open = connections.len();
+    notify.send_replace(true);
+    let drain = async { while connections.join_next().await.is_some() {} };
+    let _ = time::timeout(self.config.grace_period, drain).await;
+    let aborted = connections.len();
+    connections.shutdown().await;
+    Ok(Shutdown {
+      drained: open - aborted,
+      aborted,
+      elapsed: started.elapsed(),
+    })
+  }
+  fn configure(&self, stream: &net::TcpStream) -> io::Result<()> {
+    stream.set_nodelay(self.nodelay)?;
+    if let Some(time) = self.tcp_keepalive {
+      SockRef::from(stream).set_tcp_keepalive(&TcpKeepalive::new().with_time(time))?;
+    }
+    Ok(())
+  }
+}
+
+async fn serve<S, H>(
+  stream: S,
+  config: Config,
+  shutdown: watch::Receiver<bool>,
+  handler: Arc<H>,
+) -> Result<(), Error>
+where
+  S: AsyncRead + AsyncWrite + Unpin,
+  H: Handler<Request> + Send,
+  H::Response: IntoResponse,
+{
+  Connection::new(stream, config)
+    .with_shutdown(shutdown)
+    .serve(handler)
+    .await
+}
+
+/// What became of the connections that were open when a [`Server`] shut down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shutdown {
+  /// Connections that finished within the grace period.
+  pub drained: usize,
+  /// Connections still open when the grace period ran out, which were dropped.
+  pub aborted: usize,
+  /// Time from the shutdown signal until the last connection was gone.
+  pub elapsed: Duration,
+}
+
+/// Resolves when the process receives SIGINT (Ctrl-C) or, on Unix, SIGTERM.
+pub async fn shutdown_signal() {
+  #[cfg(unix)]
+  {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate()).expect("couldn't listen for SIGTERM");
+    tokio::select! {
+      _ = tokio::signal::ctrl_c() => {}
+      _ = terminate.recv() => {}
+    }
+  }
+  #[cfg(not(unix))]
+  let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+  use tokio::sync::oneshot;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn serves_every_listener_until_shutdown() {
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = Server::builder()
+      .bind("127.0.0.1:0")
+      .listener(taken)
+      .nodelay(true)
+      .grace_period(Duration::from_millis(50))
+      .build()
+      .unwrap();
+    let addrs = server.local_addrs();
+    assert_eq!(addrs.len(), 2);
+    let (stop, stopped) = oneshot::channel::<()>();
+    let handler = |request: Request| async move {
+      if request.uri().path() == "/hang" {
+        std::future::pending::<()>().await;
+      }
+      "hi"
+    };
+    let task = tokio::spawn(server.listen_until(handler, async {
+      stopped.await.ok();
+    }));
+
+    for addr in &addrs {
+      let mut client = net::TcpStream::connect(addr).await.unwrap();
+      client
+        .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+      let mut out = String::new();
+      client.read_to_string(&mut out).await.unwrap();
+      assert!(out.ends_with("\r\n\r\nhi"));
+    }
+    let mut idle = net::TcpStream::connect(addrs[0]).await.unwrap();
+    let mut hung = net::TcpStream::connect(addrs[1]).await.unwrap();
+    hung.write_all(b"GET /hang HTTP/1.1\r\n\r\n").await.unwrap();
+    time::sleep(Duration::from_millis(20)).await;
+    stop.send(()).unwrap();
+    let shutdown = task.await.unwrap().unwrap();
+    assert_eq!((shutdown.drained, shutdown.aborted), (1, 1));
+    assert_eq!(idle.read(&mut [0; 16]).await.unwrap(), 0);
+  }
+
+  #[test]
+  fn bind_failures_are_io_errors() {
+    let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    for builder in [
+      Server::builder().bind(taken.local_addr().unwrap()),
+      Server::builder().bind("not an address"),
+      Server::builder(),
+      Server::builder().bind("127.0.0.1:0").max_connections(0),
+    ] {
+      let err = builder.build().err().unwrap();
+      assert!(matches!(err.kind(), Kind::Io));
+    }
+  }
+}
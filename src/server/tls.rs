@@ -0,0 +1,399 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use rustls::crypto::ring;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::ServerConfig;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use crate::error::*;
+
+/// TLS settings for a [`Server`](super::Server): certificates picked by the name the client asks
+/// for (SNI), the protocols offered through ALPN, and reloading of certificates that change on
+/// disk. Made with [`Tls::builder`].
+#[derive(Clone)]
+pub struct Tls {
+  acceptor: TlsAcceptor,
+  certs: Arc<Certs>,
+  reload_interval: Duration,
+}
+
+impl Tls {
+  pub fn builder() -> TlsBuilder {
+    TlsBuilder::new()
+  }
+  /// Loads every certificate whose files changed since it was last loaded, e.g. after a renewal.
+  /// A certificate that fails to load keeps being served as it was, and the first failure is
+  /// returned.
+  pub fn reload(&self) -> Result<(), Error> {
+    let mut result = Ok(());
+    for file in self.certs.files() {
+      if let Err(e) = file.reload() {
+        result = result.and(Err(e));
+      }
+    }
+    result
+  }
+  /// Reloads changed certificates every reload interval, for as long as the server runs.
+  pub(crate) async fn watch(self) {
+    let mut interval = time::interval(self.reload_interval);
+    interval.tick().await;
+    loop {
+      interval.tick().await;
+      let tls = self.clone();
+      if let Ok(Err(e)) = tokio::task::spawn_blocking(move || tls.reload()).await {
+        eprintln!("couldn't reload certificates: {e}");
+      }
+    }
+  }
+  /// Runs the handshake on a freshly accepted connection, giving up after `timeout`.
+  pub(crate) async fn accept<S>(&self, stream: S, timeout: Duration) -> Result<TlsStream<S>, Error>
+  where
+    S: AsyncRead + AsyncWrite + Unpin,
+  {
+    match time::timeout(timeout, self.acceptor.accept(stream)).await {
+      Ok(accepted) => accepted.map_err(Error::new_io),
+      Err(_) => Err(Error::new(Kind::Timeout)),
+    }
+  }
+}
+
+pub struct TlsBuilder {
+  default: Option<(PathBuf, PathBuf)>,
+  hosts: Vec<(String, PathBuf, PathBuf)>,
+  alpn: Vec<Vec<u8>>,
+  reload_interval: Duration,
+}
+
+impl TlsBuilder {
+  pub fn new() -> Self {
+    Self {
+      default: None,
+      hosts: vec![],
+      alpn: vec![b"http/1.1".to_vec()],
+      reload_interval: Duration::from_secs(60),
+    }
+  }
+  /// The certificate chain and private key, both PEM files, for clients that ask for no host or
+  /// for one without a certificate of its own.
+  pub fn cert(mut self, cert: impl AsRef<Path>, key: impl AsRef<Path>) -> Self {
+    self.default = Some((cert.as_ref().into(), key.as_ref().into()));
+    self
+  }
+  /// The certificate chain and private key for `host`, which may start with `*.` to cover every
+  /// subdomain one level down. Exact names win over `*.` ones.
+  pub fn host(mut self, host: &str, cert: impl AsRef<Path>, key: impl AsRef<Path>) -> Self {
+    let host = host.to_ascii_lowercase();
+    self
+      .hosts
+      .push((host, cert.as_ref().into(), key.as_ref().into()));
+    self
+  }
+  /// The protocols offered through ALPN, most preferred first. Only `http/1.1` unless set.
+  pub fn alpn(mut self, protocols: &[&str]) -> Self {
+    self.alpn = protocols.iter().map(|p| p.as_bytes().to_vec()).collect();
+    self
+  }
+  /// How often certificate files are checked for changes. A minute unless set, and can't be
+  /// zero.
+  pub fn reload_interval(mut self, interval: Duration) -> Self {
+    self.reload_interval = interval;
+    self
+  }
+  /// Loads every certificate. Fails with a [`Kind::Io`] error naming the first file that
+  /// couldn't be read or parsed, for a key that doesn't match its certificate, when no
+  /// certificate was given, or for a zero reload interval.
+  pub fn build(self) -> Result<Tls, Error> {
+    if self.reload_interval.is_zero() {
+      let err = io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "reload interval must not be zero",
+      );
+      return Err(Error::new_io(err));
+    }
+    let default = match self.default {
+      Some((cert, key)) => Some(CertFile::load(cert, key)?),
+      None => None,
+    };
+    let mut hosts = self
+      .hosts
+      .into_iter()
+      .map(|(host, cert, key)| Ok((host, CertFile::load(cert, key)?)))
+      .collect::<Result<Vec<_>, Error>>()?;
+    if default.is_none() && hosts.is_empty() {
+      let err = io::Error::new(io::ErrorKind::InvalidInput, "no certificate to serve");
+      return Err(Error::new_io(err));
+    }
+    hosts.sort_by_key(|(host, _)| host.starts_with("*."));
+    let certs = Arc::new(Certs { default, hosts });
+    let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+      .with_safe_default_protocol_versions()
+      .unwrap()
+      .with_no_client_auth()
+      .with_cert_resolver(certs.clone());
+    config.alpn_protocols = self.alpn;
+    Ok(Tls {
+      acceptor: TlsAcceptor::from(Arc::new(config)),
+      certs,
+      reload_interval: self.reload_interval,
+    })
+  }
+}
+
+impl Default for TlsBuilder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[derive(Debug)]
+struct Certs {
+  default: Option<CertFile>,
+  /// Sorted so exact names come before `*.` ones.
+  hosts: Vec<(String, CertFile)>,
+}
+
+impl Certs {
+  fn files(&self) -> impl Iterator<Item = &CertFile> {
+    self.default.iter().chain(self.hosts.iter().map(|(_, f)| f))
+  }
+}
+
+impl ResolvesServerCert for Certs {
+  fn resolve(&self, hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+    let host = hello.server_name().map(str::to_ascii_lowercase);
+    let file = host
+      .and_then(|host| self.hosts.iter().find(|(name, _)| covers(name, &host)))
+      .map(|(_, file)| file)
+      .or(self.default.as_ref())?;
+    Some(file.current())
+  }
+}
+
+/// Whether a certificate registered for `name` serves `host`.
+fn covers(name: &str, host: &str) -> bool {
+  match name.strip_prefix("*.") {
+    Some(parent) => host.split_once('.').is_some_and(|(_, rest)| rest == parent),
+    None => name == host,
+  }
+}
+
+/// A certificate chain and key, with the modification times of the files they came from.
+#[derive(Debug)]
+struct CertFile {
+  cert: PathBuf,
+  key: PathBuf,
+  loaded: RwLock<(Arc<CertifiedKey>, Option<Modified>)>,
+}
+
+type Modified = (SystemTime, SystemTime);
+
+impl CertFile {
+  fn load(cert: PathBuf, key: PathBuf) -> Result<Self, Error> {
+    let modified = modified(&cert, &key);
+    let loaded = certified_key(&cert, &key)?;
+    Ok(Self {
+      cert,
+      key,
+      loaded: RwLock::new((Arc::new(loaded), modified)),
+    })
+  }
+  fn current(&self) -> Arc<CertifiedKey> {
+    self.loaded.read().unwrap().0.clone()
+  }
+  fn reload(&self) -> Result<(), Error> {
+    let modified = modified(&self.cert, &self.key);
+    if modified.is_some() && modified == self.loaded.read().unwrap().1 {
+      return Ok(());
+    }
+    let loaded = certified_key(&self.cert, &self.key)?;
+    *self.loaded.write().unwrap() = (Arc::new(loaded), modified);
+    Ok(())
+  }
+}
+
+fn modified(cert: &Path, key: &Path) -> Option<Modified> {
+  let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+  Some((modified(cert)?, modified(key)?))
+}
+
+fn certified_key(cert: &Path, key: &Path) -> Result<CertifiedKey, Error> {
+  let failed = |path: &Path, err: &dyn std::fmt::Display| {
+    let err = io::Error::new(
+      io::ErrorKind::InvalidData,
+      format!("{}: {err}", path.display()),
+    );
+    Error::new_io(err)
+  };
+  let pem = fs::read(cert).map_err(|e| failed(cert, &e))?;
+  let chain = rustls_pemfile::certs(&mut &pem[..])
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| failed(cert, &e))?;
+  if chain.is_empty() {
+    return Err(failed(cert, &"no certificates found"));
+  }
+  let pem = fs::read(key).map_err(|e| failed(key, &e))?;
+  let der = rustls_pemfile::private_key(&mut &pem[..])
+    .map_err(|e| failed(key, &e))?
+    .ok_or_else(|| failed(key, &"no private key found"))?;
+  let signing_key = ring::sign::any_supported_type(&der).map_err(|e| failed(key, &e))?;
+  let certified = CertifiedKey::new(chain, signing_key);
+  // Catches a renewal caught halfway, with the new certificate written but not its key yet.
+  certified.keys_match().map_err(|e| failed(key, &e))?;
+  Ok(certified)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::UNIX_EPOCH;
+
+  use rustls::pki_types::{CertificateDer, ServerName};
+  use rustls::{ClientConfig, RootCertStore};
+  use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+  use tokio::net::TcpStream;
+  use tokio::sync::oneshot;
+  use tokio_rustls::TlsConnector;
+
+  use super::*;
+  use crate::http::Request;
+  use crate::server::Server;
+
+  fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("web-server-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  /// Writes a new self-signed certificate for `host` to `dir`, unless `key` is false, and stamps
+  /// the files with modification time `stamp` so reloads see them change whatever the
+  /// filesystem's timestamp resolution.
+  fn write_cert(dir: &Path, host: &str, stamp: u64, key: bool) -> CertificateDer<'static> {
+    let generated = rcgen::generate_simple_self_signed(vec![host.to_string()]).unwrap();
+    let cert = dir.join(format!("{host}.crt"));
+    let key_file = dir.join(format!("{host}.key"));
+    fs::write(&cert, generated.cert.pem()).unwrap();
+    if key {
+      fs::write(&key_file, generated.key_pair.serialize_pem()).unwrap();
+    }
+    for path in [cert, key_file] {
+      let file = fs::File::options().write(true).open(path).unwrap();
+      file
+        .set_modified(UNIX_EPOCH + Duration::from_secs(stamp))
+        .unwrap();
+    }
+    generated.cert.der().clone()
+  }
+
+  fn connector(roots: &[&CertificateDer<'static>]) -> TlsConnector {
+    let mut store = RootCertStore::empty();
+    for root in roots {
+      store.add((*root).clone()).unwrap();
+    }
+    let mut config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+      .with_safe_default_protocol_versions()
+      .unwrap()
+      .with_root_certificates(store)
+      .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    TlsConnector::from(Arc::new(config))
+  }
+
+  /// Connects to `tls` asking for `host`, and returns the certificate and protocol it picked.
+  async fn handshake(
+    tls: &Tls,
+    roots: &[&CertificateDer<'static>],
+    host: &str,
+  ) -> (CertificateDer<'static>, Option<Vec<u8>>) {
+    let (client, server) = duplex(16 * 1024);
+    let name = ServerName::try_from(host.to_string()).unwrap();
+    let connect = connector(roots).connect(name, client);
+    let (client, server) = tokio::join!(connect, tls.accept(server, Duration::from_secs(1)));
+    server.unwrap();
+    let client = client.unwrap().into_inner().1;
+    let cert = client.peer_certificates().unwrap()[0].clone();
+    (cert, client.alpn_protocol().map(<[u8]>::to_vec))
+  }
+
+  #[tokio::test]
+  async fn certificates_are_picked_by_sni_and_reloaded() {
+    let dir = temp_dir("sni");
+    let localhost = write_cert(&dir, "localhost", 1, true);
+    let a = write_cert(&dir, "a.test", 1, true);
+    let tls = Tls::builder()
+      .cert(dir.join("localhost.crt"), dir.join("localhost.key"))
+      .host("A.test", dir.join("a.test.crt"), dir.join("a.test.key"))
+      .build()
+      .unwrap();
+    let roots = [&localhost, &a];
+
+    let (cert, alpn) = handshake(&tls, &roots, "a.test").await;
+    assert_eq!(cert, a);
+    assert_eq!(alpn.as_deref(), Some(&b"http/1.1"[..]));
+    assert_eq!(handshake(&tls, &roots, "localhost").await.0, localhost);
+
+    let renewed = write_cert(&dir, "a.test", 2, true);
+    tls.reload().unwrap();
+    let (cert, _) = handshake(&tls, &[&localhost, &renewed], "a.test").await;
+    assert_eq!(cert, renewed);
+
+    // A certificate without its matching key, as seen halfway through a renewal, is refused and
+    // the previous pair stays in use.
+    write_cert(&dir, "a.test", 3, false);
+    assert!(tls.reload().is_err());
+    assert_eq!(handshake(&tls, &[&renewed], "a.test").await.0, renewed);
+    fs::write(dir.join("a.test.key"), "not a key").unwrap();
+    assert!(tls.reload().is_err());
+    assert_eq!(handshake(&tls, &[&renewed], "a.test").await.0, renewed);
+
+    assert!(Tls::builder().build().is_err());
+    let zero_interval = Tls::builder()
+      .cert(dir.join("localhost.crt"), dir.join("localhost.key"))
+      .reload_interval(Duration::ZERO)
+      .build();
+    assert!(zero_interval.is_err());
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[tokio::test]
+  async fn servers_answer_over_https() {
+    let dir = temp_dir("https");
+    let cert = write_cert(&dir, "localhost", 1, true);
+    let tls = Tls::builder()
+      .cert(dir.join("localhost.crt"), dir.join("localhost.key"))
+      .build()
+      .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let server = Server::builder()
+      .bind("127.0.0.1:0")
+      .tls(tls)
+      .build()
+      .unwrap();
+    let addr = server.local_addrs()[0];
+    let (stop, stopped) = oneshot::channel::<()>();
+    let handler = |_: Request| async { "secure" };
+    let task = tokio::spawn(server.listen_until(handler, async {
+      stopped.await.ok();
+    }));
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let name = ServerName::try_from("localhost").unwrap();
+    let mut stream = connector(&[&cert]).connect(name, stream).await.unwrap();
+    stream
+      .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+      .await
+      .unwrap();
+    let mut out = String::new();
+    stream.read_to_string(&mut out).await.unwrap();
+    assert!(out.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(out.ends_with("\r\n\r\nsecure"));
+    stop.send(()).unwrap();
+    task.await.unwrap().unwrap();
+  }
+}
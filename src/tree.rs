@@ -89,18 +89,14 @@ impl<K: Ord, D> Node<K, D> {
     n.height = Self::height(&n.left).max(Self::height(&n.right)) + 1;
     Self::rebalance(n)
   }
-  pub fn get<'a>(n: &'a Box<Node<K, D>>, key: &'a K) -> Option<&'a D> {
+  pub fn get<'a>(n: &'a Node<K, D>, key: &'a K) -> Option<&'a D> {
     match key.cmp(&n.key) {
       std::cmp::Ordering::Less => {
-        let Some(l) = n.left.as_ref() else {
-          return None;
-        };
+        let l = n.left.as_ref()?;
         Self::get(l, key)
       }
       std::cmp::Ordering::Greater => {
-        let Some(r) = n.right.as_ref() else {
-          return None;
-        };
+        let r = n.right.as_ref()?;
         Self::get(r, key)
       }
       std::cmp::Ordering::Equal => Some(&n.data),
@@ -111,12 +107,12 @@ impl<K: Ord, D> Node<K, D> {
 fn print_node<K: Ord + fmt::Display, D: fmt::Display>(node: &Node<K, D>, level: usize) -> String {
   let mut result = String::new();
   if let Some(right) = &node.right {
-    result.push_str(&print_node(&right, level + 1));
+    result.push_str(&print_node(right, level + 1));
   }
   let s = format!("{}{}: {}\n", "\t".repeat(level), node.key, node.data);
   result.push_str(&s);
   if let Some(left) = &node.left {
-    result.push_str(&print_node(&left, level + 1));
+    result.push_str(&print_node(left, level + 1));
   }
   result
 }
@@ -133,10 +129,8 @@ impl<K: Ord, D> Tree<K, D> {
       None => Box::new(Node::new(key, data)),
     }));
   }
-  pub fn get<'a>(&'a self, key: &'a K) -> Option<&D> {
-    let Some(root) = self.root.as_ref() else {
-      return None;
-    };
+  pub fn get<'a>(&'a self, key: &'a K) -> Option<&'a D> {
+    let root = self.root.as_ref()?;
     Node::get(root, key)
   }
   pub fn height(&self) -> usize {